@@ -0,0 +1,49 @@
+//! Decompression of compressed benchmark files, which are passed to solvers as a decompressed
+//! copy.
+use super::*;
+use std::fs;
+
+/// A compression format, recognized by the extension of a file.
+#[derive(Copy, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub(crate) enum Compression {
+    Gzip,
+    Xz,
+    Bzip2,
+    Zstd,
+}
+
+impl Compression {
+    /// The format `file` is compressed with, if any.
+    pub(crate) fn of(file: &Path) -> Option<Compression> {
+        match file.extension()?.to_str()? {
+            "gz" => Some(Compression::Gzip),
+            "xz" => Some(Compression::Xz),
+            "bz2" => Some(Compression::Bzip2),
+            "zst" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    fn decoder(self, file: fs::File) -> Result<Box<dyn io::Read>> {
+        let file = io::BufReader::new(file);
+        Ok(match self {
+            Compression::Gzip => Box::new(flate2::bufread::MultiGzDecoder::new(file)),
+            Compression::Xz => Box::new(xz2::bufread::XzDecoder::new_multi_decoder(file)),
+            Compression::Bzip2 => Box::new(bzip2::bufread::MultiBzDecoder::new(file)),
+            Compression::Zstd => Box::new(zstd::Decoder::with_buffer(file)?),
+        })
+    }
+
+    /// Decompresses `file` into the directory `dir`, and returns the path of the decompressed
+    /// file, which is named like `file` without the compression extension.
+    pub(crate) fn decompress(self, file: &Path, dir: &Path) -> Result<PathBuf> {
+        let name = file.file_stem()
+            .with_context(|| format!("{} has no file name", file.display()))?;
+        let target = dir.join(name);
+        let mut decoder = self.decoder(open_file(file)?)?;
+        let mut out = create_file(&target)?;
+        io::copy(&mut decoder, &mut out)
+            .with_context(|| format!("failed to decompress {}", file.display()))?;
+        Ok(target)
+    }
+}
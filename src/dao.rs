@@ -2,11 +2,28 @@ use super::*;
 use anyhow::Result;
 use crate::interface::Ident;
 use std::fs;
+use std::collections::BTreeMap;
 use log::*;
 
+mod claim;
+mod sqlite;
+pub(crate) use claim::Claim;
+use sqlite::SqliteDao;
+pub(crate) use sqlite::BlobRef;
+
 #[derive(Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
 pub struct DaoConfig {
-    pub outdir: PathBuf
+    pub outdir: PathBuf,
+    pub backend: DaoBackend,
+}
+
+/// How results are stored in the output directory.
+#[derive(ValueEnum, Copy, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq, Serialize, Deserialize)]
+pub enum DaoBackend {
+    /// one directory per result, containing meta.json, stdout.txt, stderr.txt and pwd/
+    Files,
+    /// a single SQLite database results.sqlite
+    Sqlite,
 }
 
 pub(crate) fn create<P>(conf: DaoConfig) -> Result<impl Dao<P>>
@@ -15,15 +32,177 @@ where P: Benchmarker,
     //TODO get rid of this clone
     let outdir = conf.outdir;
     create_dir_all(&outdir)?;
-    Ok(DaoImpl { outdir })
+    let dao = match conf.backend {
+        DaoBackend::Files => AnyDao::Files(DaoImpl { outdir }),
+        DaoBackend::Sqlite => AnyDao::Sqlite(SqliteDao::open(outdir.join("results.sqlite"))?),
+    };
+    Ok(dao)
+}
+
+/// Copies the results of the directory based result tree `dir` into `dao`, and returns how many
+/// results have been copied. Results `dao` contains already are kept, as they might be newer
+/// than the imported ones.
+pub(crate) fn import_dir<P, D>(dir: &Path, dao: &D) -> Result<usize>
+where P: Benchmarker,
+      D: Dao<P>,
+{
+    let from = DaoImpl { outdir: dir.to_owned() };
+    let runs = Dao::<P>::stored_runs(&from)?;
+    let mut imported = 0;
+    {
+        let ui = Ui::new("Importing results", runs.len());
+        for run in runs {
+            if dao.read_result(&run)?.is_some() {
+                info!("skipping result {}, it is stored already", run);
+            } else if let Some(res) = from.read_result(&run)? {
+                dao.store_result(&res)
+                    .with_context(|| format!("failed to import result {}", run))?;
+                imported += 1;
+            }
+            ui.progress();
+        }
+    }
+    println!("imported {} results", imported);
+    Ok(imported)
 }
 
 pub(crate) trait Dao<P> 
 where P: Benchmarker
 {
-    fn store_result(&self, run: &BenchRunResult<P>) -> Result<()>;
+    /// Stores `run`, and returns the stored result, whose outputs refer to the store.
+    fn store_result(&self, run: &BenchRunResult<P>) -> Result<BenchRunResult<P>>;
     fn read_result(&self, run: &BenchRunConf<P>) -> Result<Option<BenchRunResult<P>>>;
     fn remove_result<R: std::fmt::Display>(&self, run: &BenchRunConf<P>, reason: R) -> Result<()>;
+    /// Returns all timeouts for which results of the solver of `run` might be stored.
+    fn timeouts(&self, run: &BenchRunConf<P>) -> Result<Vec<Duration>>;
+    /// Returns the configurations of all results that are currently stored.
+    fn stored_runs(&self) -> Result<Vec<BenchRunConf<P>>>;
+    /// Claims `run` for this runner instance. Returns `None` if it is claimed by another instance
+    /// sharing the same store.
+    fn claim(&self, run: &BenchRunConf<P>) -> Result<Option<Claim<'_>>>;
+    /// Returns the reason why the result of `run` has been removed, if it has been.
+    fn failure(&self, run: &BenchRunConf<P>) -> Result<Option<String>>;
+    /// Deletes the result of `run` permanently.
+    fn delete_result(&self, run: &BenchRunConf<P>) -> Result<()>;
+    /// Deletes all removed results of runs that are not claimed, and returns how many there have
+    /// been.
+    fn delete_failures(&self) -> Result<usize>;
+    /// Deletes all results of runs that are not claimed, which have not been stored completely,
+    /// e.g. because the runner has been killed while storing them. Returns how many there have
+    /// been. Complete results that have been left behind while being replaced are restored.
+    fn delete_incomplete(&self) -> Result<usize>;
+    /// Reads the cached mapped value of the stored result of `run`.
+    fn read_mapped(&self, run: &BenchRunConf<P>) -> Result<Option<MappedEntry>>;
+    /// Caches a mapped value of the stored result of `run`. It is discarded when the result is
+    /// replaced. Nothing is stored if there is no result for `run`.
+    fn store_mapped(&self, run: &BenchRunConf<P>, mapped: &MappedEntry) -> Result<()>;
+    /// Moves results, that are not stored where their run's results belong, e.g. because they
+    /// have been stored by an earlier version, to their run's location. `update` returns the run
+    /// a stored run corresponds to now, if it differs. Returns the number of moved results.
+    fn migrate<F>(&self, update: F) -> Result<usize>
+    where F: Fn(&BenchRunConf<P>) -> Option<BenchRunConf<P>>;
+}
+
+/// A mapped value of a result, cached in the result store.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct MappedEntry {
+    /// the version of the mapper the value has been computed with
+    pub version: String,
+    /// the benchmark the value has been computed for, including its metadata. `Null` for values
+    /// that have been cached before it was recorded.
+    #[serde(default)]
+    pub benchmark: serde_json::Value,
+    pub value: serde_json::Value,
+}
+
+pub(crate) enum AnyDao {
+    Files(DaoImpl),
+    Sqlite(SqliteDao),
+}
+
+impl<P> Dao<P> for AnyDao
+where P: Benchmarker
+{
+    fn store_result(&self, run: &BenchRunResult<P>) -> Result<BenchRunResult<P>> {
+        match self {
+            AnyDao::Files(d) => d.store_result(run),
+            AnyDao::Sqlite(d) => d.store_result(run),
+        }
+    }
+    fn read_result(&self, run: &BenchRunConf<P>) -> Result<Option<BenchRunResult<P>>> {
+        match self {
+            AnyDao::Files(d) => d.read_result(run),
+            AnyDao::Sqlite(d) => d.read_result(run),
+        }
+    }
+    fn remove_result<R: std::fmt::Display>(&self, run: &BenchRunConf<P>, reason: R) -> Result<()> {
+        match self {
+            AnyDao::Files(d) => d.remove_result(run, reason),
+            AnyDao::Sqlite(d) => d.remove_result(run, reason),
+        }
+    }
+    fn timeouts(&self, run: &BenchRunConf<P>) -> Result<Vec<Duration>> {
+        match self {
+            AnyDao::Files(d) => d.timeouts(run),
+            AnyDao::Sqlite(d) => d.timeouts(run),
+        }
+    }
+    fn stored_runs(&self) -> Result<Vec<BenchRunConf<P>>> {
+        match self {
+            AnyDao::Files(d) => Dao::<P>::stored_runs(d),
+            AnyDao::Sqlite(d) => Dao::<P>::stored_runs(d),
+        }
+    }
+    fn claim(&self, run: &BenchRunConf<P>) -> Result<Option<Claim<'_>>> {
+        match self {
+            AnyDao::Files(d) => d.claim(run),
+            AnyDao::Sqlite(d) => d.claim(run),
+        }
+    }
+    fn failure(&self, run: &BenchRunConf<P>) -> Result<Option<String>> {
+        match self {
+            AnyDao::Files(d) => d.failure(run),
+            AnyDao::Sqlite(d) => d.failure(run),
+        }
+    }
+    fn delete_result(&self, run: &BenchRunConf<P>) -> Result<()> {
+        match self {
+            AnyDao::Files(d) => d.delete_result(run),
+            AnyDao::Sqlite(d) => d.delete_result(run),
+        }
+    }
+    fn delete_failures(&self) -> Result<usize> {
+        match self {
+            AnyDao::Files(d) => Dao::<P>::delete_failures(d),
+            AnyDao::Sqlite(d) => Dao::<P>::delete_failures(d),
+        }
+    }
+    fn delete_incomplete(&self) -> Result<usize> {
+        match self {
+            AnyDao::Files(d) => Dao::<P>::delete_incomplete(d),
+            AnyDao::Sqlite(d) => Dao::<P>::delete_incomplete(d),
+        }
+    }
+    fn read_mapped(&self, run: &BenchRunConf<P>) -> Result<Option<MappedEntry>> {
+        match self {
+            AnyDao::Files(d) => d.read_mapped(run),
+            AnyDao::Sqlite(d) => d.read_mapped(run),
+        }
+    }
+    fn store_mapped(&self, run: &BenchRunConf<P>, mapped: &MappedEntry) -> Result<()> {
+        match self {
+            AnyDao::Files(d) => d.store_mapped(run, mapped),
+            AnyDao::Sqlite(d) => d.store_mapped(run, mapped),
+        }
+    }
+    fn migrate<F>(&self, update: F) -> Result<usize>
+    where F: Fn(&BenchRunConf<P>) -> Option<BenchRunConf<P>>
+    {
+        match self {
+            AnyDao::Files(d) => d.migrate(update),
+            AnyDao::Sqlite(d) => d.migrate(update),
+        }
+    }
 }
 
 
@@ -36,7 +215,11 @@ where P: Benchmarker,
     run: &'a BenchRunConf<P>,
     status: &'a BenchmarkStatus,
     time: &'a Duration,
+    usage: &'a Option<ResourceUsage>,
+    inputs: &'a InputFingerprints,
     exit_status: &'a Option<i32>,
+    truncated: &'a Vec<CapturedOutput>,
+    env: &'a Option<BTreeMap<String, String>>,
 }
 
 #[derive(Deserialize, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
@@ -47,7 +230,15 @@ where P: Benchmarker,
     run: BenchRunConf<P>,
     status: BenchmarkStatus,
     time: Duration,
+    #[serde(default)]
+    usage: Option<ResourceUsage>,
+    #[serde(default)]
+    inputs: InputFingerprints,
     exit_status: Option<i32>,
+    #[serde(default)]
+    truncated: Vec<CapturedOutput>,
+    #[serde(default)]
+    env: Option<BTreeMap<String, String>>,
 }
 //TODO ensure thread safety
 
@@ -55,54 +246,243 @@ pub struct DaoImpl {
     outdir: PathBuf,
 }
 
+/// The path, relative to its parent directory, results of the solver or benchmark `id` are stored
+/// at.
+///
+/// Ids that are plain relative paths, such as the ids of benchmarks in the benchmark directory,
+/// keep their directory structure. All other ids, e.g. absolute paths, or paths containing `..` or
+/// names the store uses itself, are escaped to a single file name that is suffixed by a short hash
+/// of the id, so that distinct ids are never stored at the same path.
+pub(crate) fn id_to_path(id: impl std::fmt::Display) -> PathBuf {
+    use sha2::{Digest, Sha256};
+    let id = id.to_string();
+    // hidden names are temporary directories and lock files, `pwd` holds the files of a run, and
+    // repetitions are stored at `<benchmark>.rep<n>`
+    let repetition = |name: &str| name.rsplit_once(".rep")
+        .is_some_and(|(_, n)| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+    let reserved = |name: &str| name.is_empty() || name.starts_with('.') || name == "pwd" || name.ends_with(".err") || repetition(name);
+    if !id.split('/').any(reserved) {
+        return PathBuf::from(id);
+    }
+    let escaped = id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "-_.".contains(c) { c } else { '_' })
+        .skip_while(|&c| c == '.')
+        .take(100)
+        .collect::<String>();
+    let hash = Sha256::digest(id.as_bytes()).iter().take(4).map(|b| format!("{:02x}", b)).collect::<String>();
+    PathBuf::from(format!("{}-{}", escaped, hash))
+}
+
+/// Checks that the results of distinct `ids` are stored at distinct paths, none of which contains
+/// another one. `kind` describes the ids in error messages.
+pub(crate) fn check_ids<I>(kind: &str, ids: impl IntoIterator<Item = I>) -> Result<()>
+where I: std::fmt::Display,
+{
+    let mut paths = BTreeMap::new();
+    for id in ids {
+        let id = id.to_string();
+        match paths.insert(id_to_path(&id), id.clone()) {
+            Some(other) if other == id => bail!("{} {} is part of the job multiple times", kind, id),
+            Some(other) => bail!("the results of the {}s {} and {} would be stored at the same path", kind, other, id),
+            None => {}
+        }
+    }
+    for (path, id) in &paths {
+        if let Some(other) = path.ancestors().skip(1).find_map(|dir| paths.get(dir)) {
+            bail!("the results of {} {} would be stored within the ones of {}", kind, id, other);
+        }
+    }
+    Ok(())
+}
+
 impl DaoImpl {
+    fn solver_dir<P>(&self, run: &BenchRunConf<P>) -> PathBuf
+    where P: Benchmarker,
+    {
+        PathBuf::from(&self.outdir)
+            .join(id_to_path(Ident::id(run.solver.as_ref())))
+    }
+
     fn outdir<P>(&self, run: &BenchRunConf<P>) -> PathBuf
     where P: Benchmarker,
           // <P as Benchmarker>::Solver: Ident,
           // <P as Benchmarker>::Benchmark: Ident,
     {
-        //TODO make this clean
-        macro_rules! id_to_path {
-            ($x: expr) =>  { {
-                let id = PathBuf::from(format!("{}", $x));
-                id.file_name().unwrap().to_owned()
-            }}
-        }
-        PathBuf::from(&self.outdir)
-            .join(id_to_path!(Ident::id(run.solver.as_ref())))
+        let dir = self.solver_dir(run)
             .join(format!("{}", run.timeout.as_secs()))
-            .join(id_to_path!(run.benchmark.id()))
+            .join(id_to_path(run.benchmark.id()));
+        // the first repetition is stored like a run that is not repeated, so that its result stays
+        // in place if the number of repetitions changes
+        match run.repetition {
+            0 => dir,
+            rep => sibling_with_suffix(&dir, &format!("rep{}", rep)),
+        }
+    }
+
+    /// The directory a result is written to, before it is moved to its final location. This way a
+    /// result directory is always complete, even if the runner is killed while storing it.
+    fn tmp_dir<P>(&self, run: &BenchRunConf<P>) -> PathBuf
+    where
+        P: Benchmarker
+    {
+        sibling(&self.outdir(run), "tmp")
     }
 
-    fn meta_json<P>(&self, run: &BenchRunConf<P>) -> PathBuf
+    /// The directory an existing result is moved to, while it is being replaced.
+    fn old_dir<P>(&self, run: &BenchRunConf<P>) -> PathBuf
     where
         P: Benchmarker
     {
-        self.outdir(run).join("meta.json")
+        sibling(&self.outdir(run), "old")
     }
 
-    fn pwd_dir<P>(&self, run: &BenchRunConf<P>) -> PathBuf
+    /// The directory a result is moved to when it is removed.
+    fn err_dir<P>(&self, run: &BenchRunConf<P>) -> PathBuf
     where
         P: Benchmarker
     {
-        self.outdir(run).join("pwd")
+        sibling_with_suffix(&self.outdir(run), "err")
+    }
+
+    /// The directories of all stored results, together with their runs.
+    fn stored<P>(&self) -> Result<Vec<(PathBuf, BenchRunConf<P>)>>
+    where
+        P: Benchmarker
+    {
+        let mut stored = Vec::new();
+        fn visit<P: Benchmarker>(stored: &mut Vec<(PathBuf, BenchRunConf<P>)>, dir: &Path) -> Result<()> {
+            let meta = meta_json(dir);
+            if meta.exists() {
+                match read_json::<BenchRunResultMetaOwned<P>, _>(&meta) {
+                    Ok(m) => stored.push((dir.to_owned(), m.run)),
+                    Err(e) => warn!("skipping result: {:#}", e),
+                }
+            }
+            for entry in read_dir(&dir)? {
+                let path = entry?.path();
+                let skip = path.file_name() == Some("pwd".as_ref())
+                        || path.extension() == Some("err".as_ref())
+                        // temporary directories of the atomic result storage
+                        || path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with('.'));
+                if path.is_dir() && !skip {
+                    visit(stored, &path)?;
+                }
+            }
+            Ok(())
+        }
+        visit(&mut stored, &self.outdir)?;
+        Ok(stored)
+    }
+
+    /// Removes the directories containing `dir`, e.g. the ones of its solver and timeout, if
+    /// they are empty.
+    fn remove_empty_parents(&self, dir: &Path) {
+        let mut dir = dir.parent();
+        while let Some(d) = dir {
+            if d == self.outdir || fs::remove_dir(d).is_err() {
+                break;
+            }
+            dir = d.parent();
+        }
     }
 
-    fn stdout_txt<P>(&self, run: &BenchRunConf<P>) -> PathBuf
+    /// The file whose existence marks `run` as claimed by some runner instance.
+    fn lock_file<P>(&self, run: &BenchRunConf<P>) -> PathBuf
     where
         P: Benchmarker
     {
-        self.outdir(run).join("stdout.txt")
+        sibling(&self.outdir(run), "lock")
     }
 
-    fn stderr_txt<P>(&self, run: &BenchRunConf<P>) -> PathBuf
+    /// Cleans up after a runner instance that has been killed while storing the result of `run`.
+    /// If it has been killed while replacing the result, the previous one is restored, as it is
+    /// complete. Must only be called while `run` is claimed.
+    fn recover<P>(&self, run: &BenchRunConf<P>) -> Result<()>
     where
         P: Benchmarker
     {
-        self.outdir(run).join("stderr.txt")
+        let outdir = self.outdir(run);
+        let old = self.old_dir(run);
+        if old.exists() && !outdir.exists() {
+            warn!("restoring result {}, which has been left behind while it was replaced", outdir.display());
+            rename(&old, &outdir)?;
+        }
+        for leftover in &[self.tmp_dir(run), old] {
+            if leftover.exists() {
+                warn!("deleting incompletely stored result {}", leftover.display());
+                remove_dir_all(leftover)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The directories below the output directory whose name satisfies `matches`, and which
+    /// belong to a result whose run is not claimed.
+    fn unclaimed_dirs(&self, matches: &dyn Fn(&str) -> bool) -> Result<Vec<PathBuf>> {
+        fn visit(found: &mut Vec<PathBuf>, dir: &Path, matches: &dyn Fn(&str) -> bool) -> Result<()> {
+            for entry in read_dir(&dir)? {
+                let path = entry?.path();
+                if !path.is_dir() || path.file_name() == Some("pwd".as_ref()) {
+                    continue;
+                }
+                match path.file_name().and_then(|n| n.to_str()) {
+                    Some(name) if matches(name) => found.push(path),
+                    _ => visit(found, &path, matches)?,
+                }
+            }
+            Ok(())
+        }
+        let mut found = vec![];
+        visit(&mut found, &self.outdir, matches)?;
+        // the runner instance holding the claim might be moving the directory right now
+        found.retain(|dir| !sibling(&result_dir_of(dir), "lock").exists());
+        Ok(found)
     }
 }
 
+/// The result directory that the removed or incompletely stored result `dir` belongs to.
+fn result_dir_of(dir: &Path) -> PathBuf {
+    let name = dir.file_stem().unwrap().to_string_lossy();
+    match dir.extension() {
+        Some(ext) if ext == "err" => dir.with_file_name(&*name),
+        _ => dir.with_file_name(name.strip_prefix('.').unwrap_or(&name)),
+    }
+}
+
+/// A hidden sibling of `dir`.
+fn sibling(dir: &Path, suffix: &str) -> PathBuf {
+    dir.with_file_name(format!(".{}.{}", dir.file_name().unwrap().to_string_lossy(), suffix))
+}
+
+/// The sibling of `dir` whose name is the one of `dir`, suffixed by `.<suffix>`.
+fn sibling_with_suffix(dir: &Path, suffix: &str) -> PathBuf {
+    dir.with_file_name(format!("{}.{}", dir.file_name().unwrap().to_string_lossy(), suffix))
+}
+
+fn meta_json(dir: &Path) -> PathBuf {
+    dir.join("meta.json")
+}
+
+fn pwd_dir(dir: &Path) -> PathBuf {
+    dir.join("pwd")
+}
+
+fn stdout_txt(dir: &Path) -> PathBuf {
+    dir.join("stdout.txt")
+}
+
+fn stderr_txt(dir: &Path) -> PathBuf {
+    dir.join("stderr.txt")
+}
+
+fn mapped_json(dir: &Path) -> PathBuf {
+    dir.join("mapped.json")
+}
+
+fn error_reason_txt(dir: &Path) -> PathBuf {
+    dir.join("error_reason.txt")
+}
+
 impl<P> Dao<P> for DaoImpl
 where
     P: Benchmarker
@@ -110,7 +490,7 @@ where
     fn remove_result<R: std::fmt::Display>(&self, run: &BenchRunConf<P>, reason: R) -> Result<()> {
         info!("removing result {} (reason: {})", run, reason);
         let dir = self.outdir(run);
-        let err_dir = dir.with_extension("err");
+        let err_dir = self.err_dir(run);
         if err_dir.exists() {
             remove_dir_all(&err_dir)?;
         }
@@ -120,7 +500,7 @@ where
         //     "moving result to {} (may be deleted in another run)",
         //     err_dir.display()
         // ));
-        let reason_file = err_dir.join("error_reason.txt");
+        let reason_file = error_reason_txt(&err_dir);
         if let Err(e) =
             write_vec(create_file(&reason_file)?, format!("{}", reason).as_ref())
             // create_file(reason_file).and_then(|mut reason_file| write!(reason_file, "{}", reason).co?)
@@ -131,44 +511,195 @@ where
         Ok(())
     }
 
-    fn store_result(&self, run: &BenchRunResult<P>) -> Result<()> {
+    fn store_result(&self, run: &BenchRunResult<P>) -> Result<BenchRunResult<P>> {
         info!("storing result {:?}", run);
         let BenchRunResult {
             run,
             status,
             time,
+            usage,
+            inputs,
             exit_status,
+            truncated,
+            env,
+            requested_timeout: _,
             stdout,
             stderr,
             files,
         } = run;
 
         let outdir = self.outdir(run);
-        create_dir_all(&outdir)?;
+        let tmp = self.tmp_dir(run);
+        if tmp.exists() {
+            remove_dir_all(&tmp)?;
+        }
+        create_dir_all(&tmp)?;
 
         write_json(
-            create_file(self.meta_json(run))?,
+            create_file(meta_json(&tmp))?,
             &BenchRunResultMeta {
                 run,
                 status,
                 time,
+                usage,
+                inputs,
                 exit_status,
+                truncated,
+                env,
             },
         )?;
-        write_vec(create_file(&self.stdout_txt(run))?, stdout)?;
-        write_vec(create_file(&self.stderr_txt(run))?, stderr)?;
-        let pwd = self.pwd_dir(&run);
+        copy_output(stdout, &stdout_txt(&tmp))?;
+        copy_output(stderr, &stderr_txt(&tmp))?;
+        let pwd = pwd_dir(&tmp);
         for f in files {
             let path = pwd.join(&f.name);
             std::fs::create_dir_all(path.parent().unwrap())?;
-            write_vec(create_file(&path)?, &f.bytes)?;
+            copy_output(&f.conts, &path)?;
         }
+
+        if outdir.exists() {
+            let old = self.old_dir(run);
+            if old.exists() {
+                remove_dir_all(&old)?;
+            }
+            rename(&outdir, &old)?;
+            rename(&tmp, &outdir)?;
+            remove_dir_all(&old)?;
+        } else {
+            rename(&tmp, &outdir)?;
+        }
+
+        let pwd = pwd_dir(&outdir);
+        Ok(BenchRunResult {
+            run: run.clone(),
+            status: *status,
+            time: *time,
+            usage: *usage,
+            inputs: inputs.clone(),
+            exit_status: *exit_status,
+            truncated: truncated.clone(),
+            env: env.clone(),
+            requested_timeout: None,
+            stdout: Output::file(stdout_txt(&outdir), KeepAlive::default()),
+            stderr: Output::file(stderr_txt(&outdir), KeepAlive::default()),
+            files: files.iter()
+                .map(|f| FileConts {
+                    name: f.name.clone(),
+                    conts: Output::file(pwd.join(&f.name), KeepAlive::default()),
+                })
+                .collect(),
+        })
+    }
+
+    fn timeouts(&self, run: &BenchRunConf<P>) -> Result<Vec<Duration>> {
+        let dir = self.solver_dir(run);
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+        let timeouts = process_results(read_dir(&dir)?, |entries| entries
+            .filter_map(|e| e.file_name().to_str()?.parse().ok())
+            .map(Duration::from_secs)
+            .collect())?;
+        Ok(timeouts)
+    }
+
+    fn stored_runs(&self) -> Result<Vec<BenchRunConf<P>>> {
+        Ok(self.stored()?.into_iter().map(|(_, run)| run).collect())
+    }
+
+    fn failure(&self, run: &BenchRunConf<P>) -> Result<Option<String>> {
+        let err_dir = self.err_dir(run);
+        if !err_dir.exists() {
+            return Ok(None);
+        }
+        let reason = fs::read_to_string(error_reason_txt(&err_dir))
+            .unwrap_or_else(|_| "unknown".to_owned());
+        Ok(Some(reason))
+    }
+
+    fn delete_result(&self, run: &BenchRunConf<P>) -> Result<()> {
+        info!("deleting result {}", run);
+        let dir = self.outdir(run);
+        remove_dir_all(&dir)?;
+        self.remove_empty_parents(&dir);
         Ok(())
     }
 
+    fn delete_failures(&self) -> Result<usize> {
+        // `.err` is reserved by `id_to_path`, hence no solver or benchmark is named like this
+        let failures = self.unclaimed_dirs(&|name| name.ends_with(".err"))?;
+        for f in &failures {
+            info!("deleting failed result {}", f.display());
+            remove_dir_all(f)?;
+        }
+        Ok(failures.len())
+    }
+
+    fn delete_incomplete(&self) -> Result<usize> {
+        // only the hidden siblings `store_result` creates, solvers and benchmarks may be named
+        // like `vampire.old` themselves
+        let leftover = |name: &str| name.starts_with('.') && (name.ends_with(".tmp") || name.ends_with(".old"));
+        let mut deleted = 0;
+        for dir in self.unclaimed_dirs(&leftover)? {
+            let result = result_dir_of(&dir);
+            if dir.extension() == Some("old".as_ref()) && !result.exists() {
+                // the runner has been killed while replacing the result, this is the complete one
+                warn!("restoring result {}, which has been left behind while it was replaced", result.display());
+                rename(&dir, &result)?;
+            } else {
+                warn!("deleting incomplete result {}", dir.display());
+                remove_dir_all(&dir)?;
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+
+    fn read_mapped(&self, run: &BenchRunConf<P>) -> Result<Option<MappedEntry>> {
+        let file = mapped_json(&self.outdir(run));
+        if !file.exists() {
+            return Ok(None);
+        }
+        Ok(Some(read_json(file)?))
+    }
+
+    fn store_mapped(&self, run: &BenchRunConf<P>, mapped: &MappedEntry) -> Result<()> {
+        let dir = self.outdir(run);
+        if !meta_json(&dir).exists() {
+            return Ok(());
+        }
+        // the file is written completely before it replaces the old one
+        let file = mapped_json(&dir);
+        let tmp = sibling(&file, "tmp");
+        write_json(create_file(&tmp)?, mapped)?;
+        rename(&tmp, &file)
+    }
+
+    fn claim(&self, run: &BenchRunConf<P>) -> Result<Option<Claim<'_>>> {
+        let lock = self.lock_file(run);
+        create_dir_all(lock.parent().unwrap())?;
+        let claim = match claim::lock_file(lock.clone(), claim::ttl(run))? {
+            Some(claim) => claim,
+            None => return Ok(None),
+        };
+        let claim = Claim::new(move || {
+            drop(claim);
+            // the result might have been deleted while it was claimed
+            self.remove_empty_parents(&lock);
+            Ok(())
+        });
+        self.recover(run)?;
+        Ok(Some(claim))
+    }
+
     fn read_result(&self, run: &BenchRunConf<P>) -> Result<Option<BenchRunResult<P>>> {
         info!("reading result {}", run);
         let outdir = self.outdir(run);
+        // leftovers of a runner instance that has been killed while storing the result are
+        // cleaned up when the run is claimed, unless another instance is storing it right now
+        if !outdir.exists() && (self.tmp_dir(run).exists() || self.old_dir(run).exists()) {
+            drop(Dao::<P>::claim(self, run)?);
+        }
         if !outdir.exists() {
             return Ok(None);
         }
@@ -176,15 +707,19 @@ where
             run,
             status,
             time,
+            usage,
+            inputs,
             exit_status,
-        } = read_json(self.meta_json(run))?;
+            truncated,
+            env,
+        } = read_json(meta_json(&outdir))?;
 
-        let stdout = read_vec(&self.stdout_txt(&run))?;
-        let stderr = read_vec(&self.stderr_txt(&run))?;
-        let pwd = self.pwd_dir(&run);
+        let stdout = Output::file(stdout_txt(&outdir), KeepAlive::default());
+        let stderr = Output::file(stderr_txt(&outdir), KeepAlive::default());
+        let pwd = pwd_dir(&outdir);
         let files = 
             if pwd.exists() {
-                read_file_conts(&pwd)?
+                read_file_conts(&pwd, KeepAlive::default())?
             } else {
                 vec![] // for backwards compability
             };
@@ -193,24 +728,81 @@ where
             run,
             status,
             time,
+            usage,
+            inputs,
             exit_status,
+            truncated,
+            env,
+            requested_timeout: None,
             stdout,
             stderr,
             files ,
         }))
     }
+
+    fn migrate<F>(&self, update: F) -> Result<usize>
+    where F: Fn(&BenchRunConf<P>) -> Option<BenchRunConf<P>>
+    {
+        let mut moved = 0;
+        for (dir, stored) in self.stored::<P>()? {
+            let updated = update(&stored);
+            let target = self.outdir(updated.as_ref().unwrap_or(&stored));
+            if target != dir {
+                if target.exists() || target.starts_with(&dir) {
+                    warn!("cannot move result {} to {}, the location is in use", dir.display(), target.display());
+                    continue;
+                }
+                info!("moving result {} to {}", dir.display(), target.display());
+                create_dir_all(target.parent().unwrap())?;
+                rename(&dir, &target)?;
+                self.remove_empty_parents(&dir);
+                moved += 1;
+            }
+            if let Some(run) = &updated {
+                let meta = meta_json(&target);
+                let mut value: serde_json::Value = read_json(&meta)?;
+                value["run"] = serde_json::to_value(run)?;
+                write_json(create_file(&meta)?, &value)?;
+            }
+        }
+        Ok(moved)
+    }
 }
 
-pub(crate) fn read_file_conts(dir: impl AsRef<Path>) -> Result<Vec<FileConts>> {
+/// Lists the files in `dir` recursively. Their contents are not read.
+pub(crate) fn read_file_conts(dir: impl AsRef<Path>, tmp: KeepAlive) -> Result<Vec<FileConts>> {
     let dir = dir.as_ref();
     let res = read_dir_rec(dir, |path|  
         Ok(FileConts {
-            bytes: crate::dao::read_vec(&path)?,
             name: path.strip_prefix(&dir).unwrap().to_owned(),
+            conts: Output::file(path, tmp.clone()),
         }));
     res 
 }
 
+/// Writes the contents of `output` to the file `to`, without holding it in memory.
+fn copy_output(output: &Output, to: &Path) -> Result<()> {
+    match output {
+        Output::File { path, .. } => {
+            fs::copy(path, to)
+                .with_context(|| format!("failed to copy '{}' to '{}'", path.display(), to.display()))?;
+        }
+        _ => {
+            io::copy(&mut output.open()?, &mut create_file(to)?)
+                .with_context(|| format!("failed to write '{}'", to.display()))?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn read_blob(db: &Path, blob: &BlobRef) -> Result<Vec<u8>> {
+    sqlite::read_blob(db, blob)
+}
+
+pub(crate) fn blob_len(db: &Path, blob: &BlobRef) -> Result<u64> {
+    sqlite::blob_len(db, blob)
+}
+
 // TODO move all these function to own module `fs`
 pub fn read_dir_rec<P, F, A, E>(dir: P, mut f: F) -> Result<Vec<A>, E>
     where P: AsRef<Path>,
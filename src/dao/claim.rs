@@ -0,0 +1,147 @@
+//! Claims ensure that a run is executed by only one runner instance at a time, even if several
+//! instances, possibly on different machines, share the same output directory.
+use super::*;
+use std::io;
+
+/// Identifies the runner instance holding a claim.
+#[derive(Serialize, Deserialize, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub(crate) struct Owner {
+    pub host: String,
+    pub pid: u32,
+}
+
+impl Owner {
+    pub(crate) fn current() -> Self {
+        Owner {
+            host: crate::process::hostname(),
+            pid: std::process::id(),
+        }
+    }
+
+    /// Whether a claim of this owner, which is `age` old, has been abandoned. We can only tell
+    /// whether the owner is still alive if it runs on this machine. Claims of other machines are
+    /// considered abandoned once they are older than `ttl`.
+    pub(crate) fn is_stale(&self, age: Duration, ttl: Duration) -> bool {
+        age > ttl || (self.host == crate::process::hostname() && !crate::process::is_alive(self.pid))
+    }
+}
+
+impl std::fmt::Display for Owner {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "process {} on {}", self.pid, self.host)
+    }
+}
+
+/// The time after which a claim on `run` is considered abandoned, even if we cannot tell whether
+/// its owner is still alive. A run never takes much longer than its timeout, the margin accounts
+/// for storing the result and for clock skew between machines.
+pub(crate) fn ttl<P: Benchmarker>(run: &BenchRunConf<P>) -> Duration {
+    run.timeout * 2 + Duration::from_secs(600)
+}
+
+/// The exclusive right to run a `BenchRunConf`. The claim is released when it is dropped.
+pub(crate) struct Claim<'a> {
+    release: Option<Box<dyn FnOnce() -> Result<()> + 'a>>,
+}
+
+impl<'a> Claim<'a> {
+    pub(crate) fn new(release: impl FnOnce() -> Result<()> + 'a) -> Self {
+        Claim { release: Some(Box::new(release)) }
+    }
+}
+
+impl Drop for Claim<'_> {
+    fn drop(&mut self) {
+        if let Some(release) = self.release.take() {
+            if let Err(e) = release() {
+                warn!("failed to release claim: {:#}", e);
+            }
+        }
+    }
+}
+
+/// Claims a run by atomically creating the file `lock`. If the lock file exists already, but
+/// has been abandoned by its owner, it is taken over.
+pub(crate) fn lock_file<'a>(lock: PathBuf, ttl: Duration) -> Result<Option<Claim<'a>>> {
+    loop {
+        match fs::OpenOptions::new().write(true).create_new(true).open(&lock) {
+            Ok(file) => {
+                let owner = Owner::current();
+                if let Err(e) = write_json(file, &owner) {
+                    let _ = fs::remove_file(&lock);
+                    return Err(e);
+                }
+                // an instance that has considered the lock abandoned might have replaced it
+                if !is_owner(&lock, &owner) {
+                    return Ok(None);
+                }
+                let path = lock.clone();
+                return Ok(Some(Claim::new(move || {
+                    // the lock might have been taken over, if we exceeded its ttl
+                    if !is_owner(&path, &owner) {
+                        bail!("lock file '{}' has been taken over by another runner instance", path.display());
+                    }
+                    fs::remove_file(&path)
+                        .with_context(|| format!("failed to remove lock file '{}'", path.display()))
+                })));
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                if !lock_is_stale(&lock, ttl)? {
+                    return Ok(None);
+                }
+                if !take_over(&lock, ttl)? {
+                    return Ok(None);
+                }
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("failed to create lock file '{}'", lock.display()));
+            }
+        }
+    }
+}
+
+/// Removes the abandoned lock file `lock`. It is first renamed to a name that is unique to this
+/// instance, so that of several instances taking over the lock at the same time only one
+/// removes it. If the lock has been replaced by a live one in the meantime, it is put back, and
+/// `false` is returned.
+fn take_over(lock: &Path, ttl: Duration) -> Result<bool> {
+    static TAKE_OVERS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    let owner = Owner::current();
+    let n = TAKE_OVERS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    let moved = sibling_with_suffix(lock, &format!("{}.{}.{}", owner.host, owner.pid, n));
+    match fs::rename(lock, &moved) {
+        Ok(()) => {}
+        // the lock has been released or taken over in the meantime
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(e).with_context(|| format!("failed to move lock file '{}'", lock.display())),
+    }
+    let stale = lock_is_stale(&moved, ttl)?;
+    if stale {
+        warn!("removing abandoned lock file {}", lock.display());
+    } else {
+        // hard_link does not replace a lock that has been created in the meantime
+        let _ = fs::hard_link(&moved, lock);
+    }
+    fs::remove_file(&moved)
+        .with_context(|| format!("failed to remove lock file '{}'", moved.display()))?;
+    Ok(stale)
+}
+
+fn is_owner(lock: &Path, owner: &Owner) -> bool {
+    matches!(read_json::<Owner, _>(lock), Ok(o) if o == *owner)
+}
+
+fn lock_is_stale(lock: &Path, ttl: Duration) -> Result<bool> {
+    let modified = match fs::metadata(lock) {
+        Ok(meta) => meta.modified()?,
+        // the lock has been released in the meantime
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(e).with_context(|| format!("failed to read lock file '{}'", lock.display())),
+    };
+    let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+    Ok(match read_json::<Owner, _>(lock) {
+        Ok(owner) => owner.is_stale(age, ttl),
+        // the owner might not have finished writing the lock file yet
+        Err(_) => age > ttl,
+    })
+}
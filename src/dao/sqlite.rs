@@ -0,0 +1,528 @@
+use super::*;
+use rusqlite::{params, Connection, DatabaseName, OptionalExtension, TransactionBehavior};
+use rusqlite::types::FromSql;
+use super::claim::{self, Owner};
+use std::io::{self, Read};
+use std::sync::Mutex;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS results (
+        solver      TEXT    NOT NULL,
+        timeout     INTEGER NOT NULL,
+        benchmark   TEXT    NOT NULL,
+        repetition  INTEGER NOT NULL, -- -1 for the first repetition
+        status      TEXT    NOT NULL,
+        time        REAL    NOT NULL,
+        exit_status INTEGER,
+        meta        TEXT    NOT NULL, -- the contents of meta.json in the directory based store
+        stdout      BLOB    NOT NULL,
+        stderr      BLOB    NOT NULL,
+        PRIMARY KEY (solver, timeout, benchmark, repetition)
+    );
+    CREATE INDEX IF NOT EXISTS results_by_benchmark ON results (benchmark, solver);
+    CREATE INDEX IF NOT EXISTS results_by_status ON results (status);
+
+    CREATE TABLE IF NOT EXISTS files (
+        solver      TEXT    NOT NULL,
+        timeout     INTEGER NOT NULL,
+        benchmark   TEXT    NOT NULL,
+        repetition  INTEGER NOT NULL,
+        name        TEXT    NOT NULL,
+        bytes       BLOB    NOT NULL,
+        PRIMARY KEY (solver, timeout, benchmark, repetition, name)
+    );
+
+    -- the counterpart of the *.err directories of the directory based store
+    CREATE TABLE IF NOT EXISTS removed_results (
+        solver      TEXT    NOT NULL,
+        timeout     INTEGER NOT NULL,
+        benchmark   TEXT    NOT NULL,
+        repetition  INTEGER NOT NULL,
+        reason      TEXT    NOT NULL,
+        meta        TEXT,
+        stdout      BLOB,
+        stderr      BLOB,
+        PRIMARY KEY (solver, timeout, benchmark, repetition)
+    );
+
+    -- the counterpart of mapped.json in the directory based store
+    CREATE TABLE IF NOT EXISTS mapped (
+        solver      TEXT    NOT NULL,
+        timeout     INTEGER NOT NULL,
+        benchmark   TEXT    NOT NULL,
+        repetition  INTEGER NOT NULL,
+        version     TEXT    NOT NULL,
+        value       TEXT    NOT NULL,
+        benchmark_json TEXT,          -- the benchmark the value has been computed for
+        PRIMARY KEY (solver, timeout, benchmark, repetition)
+    );
+
+    -- the counterpart of the lock files of the directory based store
+    CREATE TABLE IF NOT EXISTS claims (
+        solver      TEXT    NOT NULL,
+        timeout     INTEGER NOT NULL,
+        benchmark   TEXT    NOT NULL,
+        repetition  INTEGER NOT NULL,
+        host        TEXT    NOT NULL,
+        pid         INTEGER NOT NULL,
+        created     INTEGER NOT NULL, -- seconds since the unix epoch
+        PRIMARY KEY (solver, timeout, benchmark, repetition)
+    );
+";
+
+const KEY: &str = "solver = ?1 AND timeout = ?2 AND benchmark = ?3 AND repetition = ?4";
+
+/// Stores results in a single SQLite database, instead of one directory per result.
+pub struct SqliteDao {
+    file: PathBuf,
+    conn: Mutex<Connection>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub(crate) struct Key {
+    solver: String,
+    timeout: i64,
+    benchmark: String,
+    repetition: i64,
+}
+
+impl Key {
+    fn new<P: Benchmarker>(run: &BenchRunConf<P>) -> Self {
+        Key {
+            solver: run.solver().id().to_string(),
+            timeout: run.timeout.as_secs() as i64,
+            benchmark: run.benchmark().id().to_string(),
+            // the first repetition shares the key of a run that is not repeated
+            repetition: match run.repetition {
+                0 => -1,
+                rep => rep as i64,
+            },
+        }
+    }
+}
+
+impl SqliteDao {
+    pub(crate) fn open(file: impl AsRef<Path>) -> Result<Self> {
+        let file = file.as_ref();
+        let mut conn = Connection::open(file)
+            .with_context(|| format!("failed to open database {}", file.display()))?;
+        // the database may be shared with other runner instances
+        conn.busy_timeout(Duration::from_secs(60))?;
+        conn.execute_batch(SCHEMA)
+            .with_context(|| format!("failed to initialize database {}", file.display()))?;
+        // databases created before the benchmark of mapped values has been recorded
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        if tx.prepare("SELECT benchmark_json FROM mapped LIMIT 0").is_err() {
+            tx.execute_batch("ALTER TABLE mapped ADD COLUMN benchmark_json TEXT")?;
+        }
+        tx.commit()
+            .with_context(|| format!("failed to initialize database {}", file.display()))?;
+        Ok(SqliteDao { file: file.to_owned(), conn: Mutex::new(conn) })
+    }
+
+    fn blob(&self, blob: BlobRef) -> Output {
+        Output::Blob { db: self.file.clone(), blob }
+    }
+}
+
+/// Identifies an output of a stored result by the primary key of the row it is stored in. Unlike
+/// a rowid, the key stays valid if the database is vacuumed.
+#[derive(Serialize, Deserialize, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub(crate) enum BlobRef {
+    Stdout(Key),
+    Stderr(Key),
+    /// a file written by the run, identified by its name
+    File(Key, String),
+}
+
+impl BlobRef {
+    fn table_and_column(&self) -> (&'static str, &'static str) {
+        match self {
+            BlobRef::Stdout(_) => ("results", "stdout"),
+            BlobRef::Stderr(_) => ("results", "stderr"),
+            BlobRef::File(..) => ("files", "bytes"),
+        }
+    }
+
+    /// Evaluates `expr` on the row the blob is stored in.
+    fn select<T: FromSql>(&self, conn: &Connection, expr: &str) -> rusqlite::Result<T> {
+        let (table, _) = self.table_and_column();
+        match self {
+            BlobRef::Stdout(k) | BlobRef::Stderr(k) => conn.query_row(
+                &format!("SELECT {} FROM {} WHERE {}", expr, table, KEY),
+                params![k.solver, k.timeout, k.benchmark, k.repetition],
+                |row| row.get(0),
+            ),
+            BlobRef::File(k, name) => conn.query_row(
+                &format!("SELECT {} FROM {} WHERE {} AND name = ?5", expr, table, KEY),
+                params![k.solver, k.timeout, k.benchmark, k.repetition, name],
+                |row| row.get(0),
+            ),
+        }
+    }
+}
+
+fn open_read_only(db: &Path) -> Result<Connection> {
+    let conn = Connection::open_with_flags(db, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("failed to open database {}", db.display()))?;
+    conn.busy_timeout(Duration::from_secs(60))?;
+    Ok(conn)
+}
+
+/// Reads a single blob of the database `db`. Outputs of stored results are read this way, such
+/// that they need not be held in memory.
+pub(crate) fn read_blob(db: &Path, blob: &BlobRef) -> Result<Vec<u8>> {
+    let (_, column) = blob.table_and_column();
+    blob.select(&open_read_only(db)?, column)
+        .with_context(|| format!("failed to read {:?} in {}", blob, db.display()))
+}
+
+/// The length of a single blob of the database `db`.
+pub(crate) fn blob_len(db: &Path, blob: &BlobRef) -> Result<u64> {
+    let (_, column) = blob.table_and_column();
+    let len: i64 = blob.select(&open_read_only(db)?, &format!("length({})", column))
+        .with_context(|| format!("failed to read {:?} in {}", blob, db.display()))?;
+    Ok(len as u64)
+}
+
+/// Streams `output` into the blob `column` of the row `rowid` of `table`, which must have been
+/// inserted with a zeroblob of length `len`. This way outputs are never held in memory as a whole.
+fn write_blob(conn: &Connection, table: &str, column: &str, rowid: i64, output: &Output, len: u64) -> Result<()> {
+    let mut blob = conn.blob_open(DatabaseName::Main, table, column, rowid, false)?;
+    let written = io::copy(&mut output.open()?.take(len), &mut blob)
+        .with_context(|| format!("failed to write {}.{}", table, column))?;
+    ensure!(written == len, "output has been truncated while it was stored");
+    Ok(())
+}
+
+impl<P> Dao<P> for SqliteDao
+where
+    P: Benchmarker
+{
+    fn remove_result<R: std::fmt::Display>(&self, run: &BenchRunConf<P>, reason: R) -> Result<()> {
+        info!("removing result {} (reason: {})", run, reason);
+        let k = Key::new(run);
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO removed_results (solver, timeout, benchmark, repetition, reason, meta, stdout, stderr)
+             VALUES (?1, ?2, ?3, ?4, ?5,
+                 (SELECT meta   FROM results WHERE solver = ?1 AND timeout = ?2 AND benchmark = ?3 AND repetition = ?4),
+                 (SELECT stdout FROM results WHERE solver = ?1 AND timeout = ?2 AND benchmark = ?3 AND repetition = ?4),
+                 (SELECT stderr FROM results WHERE solver = ?1 AND timeout = ?2 AND benchmark = ?3 AND repetition = ?4))",
+            params![k.solver, k.timeout, k.benchmark, k.repetition, reason.to_string()],
+        )?;
+        tx.execute(&format!("DELETE FROM results WHERE {}", KEY), params![k.solver, k.timeout, k.benchmark, k.repetition])?;
+        tx.execute(&format!("DELETE FROM files WHERE {}", KEY), params![k.solver, k.timeout, k.benchmark, k.repetition])?;
+        tx.execute(&format!("DELETE FROM mapped WHERE {}", KEY), params![k.solver, k.timeout, k.benchmark, k.repetition])?;
+        tx.commit().context("failed to remove result")?;
+        Ok(())
+    }
+
+    fn store_result(&self, run: &BenchRunResult<P>) -> Result<BenchRunResult<P>> {
+        info!("storing result {:?}", run);
+        let BenchRunResult {
+            run,
+            status,
+            time,
+            usage,
+            inputs,
+            exit_status,
+            truncated,
+            env,
+            requested_timeout: _,
+            stdout,
+            stderr,
+            files,
+        } = run;
+        let meta = serde_json::to_string_pretty(&BenchRunResultMeta {
+            run,
+            status,
+            time,
+            usage,
+            inputs,
+            exit_status,
+            truncated,
+            env,
+        })?;
+        let stdout_len = stdout.len()?;
+        let stderr_len = stderr.len()?;
+
+        let k = Key::new(run);
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO results (solver, timeout, benchmark, repetition, status, time, exit_status, meta, stdout, stderr)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, zeroblob(?9), zeroblob(?10))",
+            params![k.solver, k.timeout, k.benchmark, k.repetition, format!("{:?}", status), time.as_secs_f64(), exit_status, meta, stdout_len as i64, stderr_len as i64],
+        )?;
+        let rowid = tx.last_insert_rowid();
+        write_blob(&tx, "results", "stdout", rowid, stdout, stdout_len)?;
+        write_blob(&tx, "results", "stderr", rowid, stderr, stderr_len)?;
+        tx.execute(&format!("DELETE FROM files WHERE {}", KEY), params![k.solver, k.timeout, k.benchmark, k.repetition])?;
+        tx.execute(&format!("DELETE FROM mapped WHERE {}", KEY), params![k.solver, k.timeout, k.benchmark, k.repetition])?;
+        let mut stored_files = vec![];
+        for f in files {
+            let name = f.name.to_string_lossy().into_owned();
+            let len = f.conts.len()?;
+            tx.execute(
+                "INSERT INTO files (solver, timeout, benchmark, repetition, name, bytes) VALUES (?1, ?2, ?3, ?4, ?5, zeroblob(?6))",
+                params![k.solver, k.timeout, k.benchmark, k.repetition, name, len as i64],
+            )?;
+            write_blob(&tx, "files", "bytes", tx.last_insert_rowid(), &f.conts, len)?;
+            stored_files.push(FileConts {
+                name: f.name.clone(),
+                conts: self.blob(BlobRef::File(k.clone(), name)),
+            });
+        }
+        tx.commit().context("failed to store result")?;
+        Ok(BenchRunResult {
+            run: run.clone(),
+            status: *status,
+            time: *time,
+            usage: *usage,
+            inputs: inputs.clone(),
+            exit_status: *exit_status,
+            truncated: truncated.clone(),
+            env: env.clone(),
+            requested_timeout: None,
+            stdout: self.blob(BlobRef::Stdout(k.clone())),
+            stderr: self.blob(BlobRef::Stderr(k)),
+            files: stored_files,
+        })
+    }
+
+    fn timeouts(&self, run: &BenchRunConf<P>) -> Result<Vec<Duration>> {
+        let k = Key::new(run);
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached("SELECT DISTINCT timeout FROM results WHERE solver = ?1")?;
+        let timeouts = stmt.query_map(params![k.solver], |row| row.get::<_, i64>(0))?
+            .map(|t| Ok(Duration::from_secs(t? as u64)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(timeouts)
+    }
+
+    fn read_result(&self, run: &BenchRunConf<P>) -> Result<Option<BenchRunResult<P>>> {
+        info!("reading result {}", run);
+        let k = Key::new(run);
+        let conn = self.conn.lock().unwrap();
+        let row = conn.query_row(
+            &format!("SELECT meta FROM results WHERE {}", KEY),
+            params![k.solver, k.timeout, k.benchmark, k.repetition],
+            |row| row.get::<_, String>(0),
+        ).optional()?;
+        let meta = match row {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        let BenchRunResultMetaOwned {
+            run,
+            status,
+            time,
+            usage,
+            inputs,
+            exit_status,
+            truncated,
+            env,
+        } = serde_json::from_str(&meta)
+            .with_context(|| format!("failed to parse stored metadata of {}", run))?;
+
+        let mut stmt = conn.prepare_cached(&format!("SELECT name FROM files WHERE {} ORDER BY name", KEY))?;
+        let files = stmt.query_map(params![k.solver, k.timeout, k.benchmark, k.repetition], |row| {
+                let name = row.get::<_, String>(0)?;
+                Ok(FileConts {
+                    name: PathBuf::from(&name),
+                    conts: self.blob(BlobRef::File(k.clone(), name)),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(Some(BenchRunResult {
+            run,
+            status,
+            time,
+            usage,
+            inputs,
+            exit_status,
+            truncated,
+            env,
+            requested_timeout: None,
+            stdout: self.blob(BlobRef::Stdout(k.clone())),
+            stderr: self.blob(BlobRef::Stderr(k)),
+            files,
+        }))
+    }
+
+    fn stored_runs(&self) -> Result<Vec<BenchRunConf<P>>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT meta FROM results")?;
+        let runs = stmt.query_map(params![], |row| row.get::<_, String>(0))?
+            .map(|meta| Ok(serde_json::from_str::<BenchRunResultMetaOwned<P>>(&meta?)?.run))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(runs)
+    }
+
+    fn failure(&self, run: &BenchRunConf<P>) -> Result<Option<String>> {
+        let k = Key::new(run);
+        let conn = self.conn.lock().unwrap();
+        let reason = conn.query_row(
+            &format!("SELECT reason FROM removed_results WHERE {}", KEY),
+            params![k.solver, k.timeout, k.benchmark, k.repetition],
+            |row| row.get(0),
+        ).optional()?;
+        Ok(reason)
+    }
+
+    fn delete_result(&self, run: &BenchRunConf<P>) -> Result<()> {
+        info!("deleting result {}", run);
+        let k = Key::new(run);
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(&format!("DELETE FROM results WHERE {}", KEY), params![k.solver, k.timeout, k.benchmark, k.repetition])?;
+        tx.execute(&format!("DELETE FROM files WHERE {}", KEY), params![k.solver, k.timeout, k.benchmark, k.repetition])?;
+        tx.execute(&format!("DELETE FROM mapped WHERE {}", KEY), params![k.solver, k.timeout, k.benchmark, k.repetition])?;
+        tx.commit().context("failed to delete result")?;
+        Ok(())
+    }
+
+    fn delete_failures(&self) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let deleted = conn.execute(
+            "DELETE FROM removed_results WHERE NOT EXISTS (SELECT 1 FROM claims c
+             WHERE c.solver = removed_results.solver AND c.timeout = removed_results.timeout
+             AND c.benchmark = removed_results.benchmark AND c.repetition = removed_results.repetition)",
+            params![],
+        )?;
+        Ok(deleted)
+    }
+
+    fn delete_incomplete(&self) -> Result<usize> {
+        // results are stored in a single transaction
+        Ok(0)
+    }
+
+    fn read_mapped(&self, run: &BenchRunConf<P>) -> Result<Option<MappedEntry>> {
+        let k = Key::new(run);
+        let conn = self.conn.lock().unwrap();
+        let row = conn.query_row(
+            &format!("SELECT version, value, benchmark_json FROM mapped WHERE {}", KEY),
+            params![k.solver, k.timeout, k.benchmark, k.repetition],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?)),
+        ).optional()?;
+        Ok(match row {
+            Some((version, value, benchmark)) => Some(MappedEntry {
+                version,
+                benchmark: match benchmark {
+                    Some(b) => serde_json::from_str(&b)
+                        .with_context(|| format!("failed to parse cached mapped value of {}", run))?,
+                    None => serde_json::Value::Null,
+                },
+                value: serde_json::from_str(&value)
+                    .with_context(|| format!("failed to parse cached mapped value of {}", run))?,
+            }),
+            None => None,
+        })
+    }
+
+    fn store_mapped(&self, run: &BenchRunConf<P>, mapped: &MappedEntry) -> Result<()> {
+        let k = Key::new(run);
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            &format!("INSERT OR REPLACE INTO mapped (solver, timeout, benchmark, repetition, version, value, benchmark_json)
+             SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7 WHERE EXISTS (SELECT 1 FROM results WHERE {})", KEY),
+            params![k.solver, k.timeout, k.benchmark, k.repetition, mapped.version, mapped.value.to_string(), mapped.benchmark.to_string()],
+        )?;
+        Ok(())
+    }
+
+    fn claim(&self, run: &BenchRunConf<P>) -> Result<Option<Claim<'_>>> {
+        let k = Key::new(run);
+        let owner = Owner::current();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+        let mut conn = self.conn.lock().unwrap();
+        // take the write lock right away, so that no other instance can claim the run in between
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let existing = tx.query_row(
+            &format!("SELECT host, pid, created FROM claims WHERE {}", KEY),
+            params![k.solver, k.timeout, k.benchmark, k.repetition],
+            |row| Ok((Owner { host: row.get(0)?, pid: row.get(1)? }, row.get::<_, i64>(2)?)),
+        ).optional()?;
+        if let Some((other, created)) = existing {
+            let age = Duration::from_secs(now.saturating_sub(created).max(0) as u64);
+            if !other.is_stale(age, claim::ttl(run)) {
+                return Ok(None);
+            }
+            warn!("removing abandoned claim on {} by {}", run, other);
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO claims (solver, timeout, benchmark, repetition, host, pid, created)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![k.solver, k.timeout, k.benchmark, k.repetition, owner.host, owner.pid, now],
+        )?;
+        tx.commit().context("failed to claim run")?;
+        Ok(Some(Claim::new(move || {
+            self.conn.lock().unwrap().execute(
+                &format!("DELETE FROM claims WHERE {} AND host = ?5 AND pid = ?6", KEY),
+                params![k.solver, k.timeout, k.benchmark, k.repetition, owner.host, owner.pid],
+            )?;
+            Ok(())
+        })))
+    }
+
+    fn migrate<F>(&self, update: F) -> Result<usize>
+    where F: Fn(&BenchRunConf<P>) -> Option<BenchRunConf<P>>
+    {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let rows = {
+            let mut stmt = tx.prepare("SELECT rowid, solver, timeout, benchmark, repetition, meta FROM results")?;
+            let rows = stmt.query_map(params![], |row| Ok((
+                    row.get::<_, i64>(0)?,
+                    Key { solver: row.get(1)?, timeout: row.get(2)?, benchmark: row.get(3)?, repetition: row.get(4)? },
+                    row.get::<_, String>(5)?,
+                )))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            rows
+        };
+        let mut moved = 0;
+        for (rowid, old, meta) in rows {
+            let mut value: serde_json::Value = serde_json::from_str(&meta)?;
+            let stored = match serde_json::from_value::<BenchRunResultMetaOwned<P>>(value.clone()) {
+                Ok(m) => m.run,
+                Err(e) => {
+                    warn!("skipping result {} {}: {:#}", old.solver, old.benchmark, e);
+                    continue;
+                }
+            };
+            let updated = update(&stored);
+            let run = updated.as_ref().unwrap_or(&stored);
+            let new = Key::new(run);
+            if updated.is_some() {
+                value["run"] = serde_json::to_value(run)?;
+                tx.execute("UPDATE results SET meta = ?1 WHERE rowid = ?2", params![serde_json::to_string_pretty(&value)?, rowid])?;
+            }
+            if new == old {
+                continue;
+            }
+            let in_use = tx.query_row(
+                &format!("SELECT 1 FROM results WHERE {}", KEY),
+                params![new.solver, new.timeout, new.benchmark, new.repetition],
+                |_| Ok(()),
+            ).optional()?.is_some();
+            if in_use {
+                warn!("cannot move result {} {} to {} {}, there is a result already", old.solver, old.benchmark, new.solver, new.benchmark);
+                continue;
+            }
+            info!("moving result {} {} to {} {}", old.solver, old.benchmark, new.solver, new.benchmark);
+            for table in &["results", "files", "mapped"] {
+                tx.execute(
+                    &format!("UPDATE {} SET solver = ?5, timeout = ?6, benchmark = ?7, repetition = ?8 WHERE {}", table, KEY),
+                    params![old.solver, old.timeout, old.benchmark, old.repetition, new.solver, new.timeout, new.benchmark, new.repetition],
+                )?;
+            }
+            moved += 1;
+        }
+        tx.commit().context("failed to migrate results")?;
+        Ok(moved)
+    }
+}
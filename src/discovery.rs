@@ -0,0 +1,175 @@
+//! Discovery of the benchmarks of a job. Benchmarks are either the files of a benchmark
+//! directory, or the files listed in a manifest, which is either a list file containing one path
+//! per line, or a JSON file (`*.json`) that may attach metadata to each benchmark:
+//!
+//! ```json
+//! [
+//!     "QF_BV/p1.smt2",
+//!     { "path": "QF_BV/p2.smt2", "status": "unsat" }
+//! ]
+//! ```
+//!
+//! Paths in manifests are relative to the manifest's directory, and are used as benchmark ids.
+//! Sidecar files (`*.meta.json`) holding the metadata of a benchmark are not discovered as
+//! benchmarks themselves.
+use super::*;
+use glob::{MatchOptions, Pattern};
+use std::collections::BTreeSet;
+use std::fs;
+use log::*;
+use serde_json::{Map, Value};
+
+/// Selects which files of the benchmark directory are benchmarks.
+#[derive(Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub struct BenchmarkFilter {
+    /// descend into subdirectories, instead of only using the files directly in the directory
+    pub recursive: bool,
+    /// if non-empty, only files matching one of these patterns are benchmarks
+    pub include: Vec<Pattern>,
+    /// files matching one of these patterns are no benchmarks
+    pub exclude: Vec<Pattern>,
+    /// if non-empty, only files with one of these extensions (without leading dot) are benchmarks
+    pub extensions: Vec<String>,
+}
+
+impl BenchmarkFilter {
+    /// Whether the file `rel`, given relative to the benchmark directory, is a benchmark.
+    ///
+    /// Patterns containing a `/` are matched against the relative path, where `*` does not match
+    /// a `/`, but `**` does. Patterns without a `/` are matched against the file name only.
+    pub fn matches(&self, rel: &Path) -> bool {
+        let options = MatchOptions { require_literal_separator: true, ..MatchOptions::new() };
+        let file_name = rel.file_name().map(Path::new).unwrap_or(rel);
+        let matches = |p: &Pattern| if p.as_str().contains('/') {
+            p.matches_path_with(rel, options)
+        } else {
+            p.matches_path_with(file_name, options)
+        };
+        let name = file_name.to_string_lossy();
+        (self.extensions.is_empty() || self.extensions.iter().any(|e| name.ends_with(&format!(".{}", e))))
+            && (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+}
+
+/// Collections of benchmarks that can be discovered in a benchmark directory, or read from a
+/// benchmark manifest.
+pub trait FromBenchDir: Sized {
+    fn from_bench_dir(dir: &Path, filter: &BenchmarkFilter) -> Result<Self>;
+}
+
+/// Benchmarks that are read from a single file.
+///
+/// `run_with_opts` and the other entry points used to read benchmarks using `FromDir`. A
+/// benchmark type that implements only `FromDir` keeps its former behaviour, identifying
+/// benchmarks by their file name, by implementing `from_bench_file` as `Self::from_dir(file)`.
+pub trait FromBenchFile: Sized {
+    /// `id` is the path of `file` relative to the benchmark directory. It identifies the benchmark
+    /// in the result store, hence it must be unique within the job.
+    fn from_bench_file(file: &Path, id: &Path) -> Result<Self>;
+
+    /// Creates a benchmark listed in a JSON manifest, where `meta` are the fields of its entry
+    /// besides its path. The metadata is ignored by default.
+    fn from_manifest_entry(file: &Path, id: &Path, meta: &Map<String, Value>) -> Result<Self> {
+        let _ = meta;
+        Self::from_bench_file(file, id)
+    }
+}
+
+impl<A> FromBenchDir for Vec<Arc<A>>
+where A: FromBenchFile
+{
+    fn from_bench_dir(dir: &Path, filter: &BenchmarkFilter) -> Result<Self> {
+        if dir.is_file() {
+            return read_manifest(dir, filter);
+        }
+        let mut files = vec![];
+        let mut visited = BTreeSet::new();
+        visited.insert(canonical_dir(dir)?);
+        collect_files(dir, Path::new(""), filter, &mut visited, &mut files)?;
+        files.sort();
+        files.into_iter()
+            .map(|rel| Ok(Arc::new(A::from_bench_file(&dir.join(&rel), &rel)?)))
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ManifestEntry {
+    Path(PathBuf),
+    WithMeta {
+        path: PathBuf,
+        #[serde(flatten)]
+        meta: Map<String, Value>,
+    },
+}
+
+/// Reads the benchmarks listed in `manifest` that match `filter`.
+fn read_manifest<A: FromBenchFile>(manifest: &Path, filter: &BenchmarkFilter) -> Result<Vec<Arc<A>>> {
+    let conts = fs::read_to_string(manifest)
+        .with_context(|| format!("failed to read benchmark manifest {}", manifest.display()))?;
+    let entries: Vec<ManifestEntry> = if manifest.extension() == Some("json".as_ref()) {
+        serde_json::from_str(&conts)
+            .with_context(|| format!("failed to parse benchmark manifest {}", manifest.display()))?
+    } else {
+        conts.lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| ManifestEntry::Path(l.into()))
+            .collect()
+    };
+    let dir = manifest.parent().unwrap_or_else(|| Path::new(""));
+    entries.into_iter()
+        .filter(|e| match e {
+            ManifestEntry::Path(path) | ManifestEntry::WithMeta { path, .. } => filter.matches(path),
+        })
+        .map(|e| {
+            let (id, meta) = match e {
+                ManifestEntry::Path(path) => (path, None),
+                ManifestEntry::WithMeta { path, meta } => (path, Some(meta)),
+            };
+            let file = dir.join(&id);
+            if !file.is_file() {
+                bail!("benchmark {} listed in {} does not exist", id.display(), manifest.display());
+            }
+            Ok(Arc::new(match meta {
+                Some(meta) => A::from_manifest_entry(&file, &id, &meta)?,
+                None => A::from_bench_file(&file, &id)?,
+            }))
+        })
+        .collect()
+}
+
+fn is_sidecar(file: &Path) -> bool {
+    file.to_string_lossy().ends_with(ids::SIDECAR_SUFFIX)
+}
+
+fn canonical_dir(dir: &Path) -> Result<PathBuf> {
+    dir.canonicalize()
+        .with_context(|| format!("failed to canonicalize {}", dir.display()))
+}
+
+/// Collects the benchmarks below `root.join(rel)`. Symlinked directories are followed, but each
+/// directory is searched only once, as links might form a cycle. `visited` holds the canonical
+/// paths of the directories that have been searched already.
+fn collect_files(root: &Path, rel: &Path, filter: &BenchmarkFilter, visited: &mut BTreeSet<PathBuf>, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in read_dir(&root.join(rel))? {
+        let entry = entry?;
+        let path = entry.path();
+        let rel = rel.join(entry.file_name());
+        if path.is_dir() {
+            if !filter.recursive {
+                continue;
+            }
+            if visited.insert(canonical_dir(&path)?) {
+                collect_files(root, &rel, filter, visited, files)?;
+            } else {
+                warn!("skipping benchmark directory {}, it has been searched already", path.display());
+            }
+        } else if filter.matches(&rel) && !is_sidecar(&rel) {
+            files.push(rel);
+        }
+    }
+    Ok(())
+}
@@ -4,6 +4,7 @@ use std::fmt;
 use std::io;
 use std::process::*;
 use std::sync::Arc;
+use std::collections::BTreeMap;
 use derivative::*;
 use std::result::Result::Ok;
 
@@ -11,7 +12,11 @@ impl<P> fmt::Display for BenchRunConf<P>
     where P: Benchmarker + ?Sized
 {
     fn fmt(&self, w: &mut fmt::Formatter) -> fmt::Result {
-        write!(w, "{} {}", self.solver.id(), self.benchmark.id())
+        write!(w, "{} {}", self.solver.id(), self.benchmark.id())?;
+        if self.seed.is_some() {
+            write!(w, " (repetition {})", self.repetition)?;
+        }
+        Ok(())
     }
 }
 
@@ -22,6 +27,15 @@ pub struct BenchRunConf<P>
     where P: Benchmarker + ?Sized
 {
     pub timeout: Duration,
+    /// memory limit in megabytes
+    #[serde(default)]
+    pub memory_limit: Option<u64>,
+    /// index of the repetition of this run
+    #[serde(default)]
+    pub repetition: usize,
+    /// seed passed to the solver, only present if the job consists of multiple repetitions
+    #[serde(default)]
+    pub seed: Option<u64>,
     #[serde(bound(serialize = "P: Benchmarker", deserialize = "P: Benchmarker"))]
     pub benchmark: Arc<P::Benchmark>,
     #[serde(bound(serialize = "P: Benchmarker", deserialize = "P: Benchmarker"))]
@@ -40,12 +54,12 @@ impl<P> BenchRunConf<P>
         self.solver.as_ref()
     }
 
-    pub fn to_command<'a>(&self) -> Command {
-        self.solver().to_command(&self.benchmark, &self.timeout)
+    pub fn to_command<'a>(&self, outdir: &Path) -> Command {
+        self.solver().to_command(&self.benchmark, &self.timeout, self.seed, outdir)
     }
 
     pub fn display_command(&self) -> impl fmt::Display {
-        self.solver().show_command(&self.benchmark, &self.timeout)
+        self.solver().show_command(&self.benchmark, &self.timeout, self.seed)
     }
 }
 
@@ -58,6 +72,10 @@ pub struct JobConfig<P>
     pub solvers: Vec<Arc<P::Solver>>,
     pub benchmarks: Vec<Arc<P::Benchmark>>,
     pub timeout: Duration,
+    /// memory limit in megabytes
+    pub memory_limit: Option<u64>,
+    /// how often each solver is run on each benchmark
+    pub repetitions: usize,
 }
 
 
@@ -74,19 +92,190 @@ impl<P> JobConfig<P>
     pub fn timeout(&self) -> Duration {
         self.timeout
     }
+    pub fn memory_limit(&self) -> Option<u64> {
+        self.memory_limit
+    }
+    pub fn repetitions(&self) -> usize {
+        self.repetitions
+    }
+    /// All runs this job consists of, i.e. every repetition of every solver on every benchmark.
+    pub fn runs(&self) -> Vec<BenchRunConf<P>> {
+        let reps = self.repetitions;
+        iproduct!(&self.benchmarks, &self.solvers, 0..reps)
+            .map(|(benchmark, solver, repetition)| BenchRunConf {
+                timeout: self.timeout,
+                memory_limit: self.memory_limit,
+                repetition,
+                seed: if reps > 1 { Some(repetition as u64) } else { None },
+                benchmark: benchmark.clone(),
+                solver: solver.clone(),
+            })
+            .collect()
+    }
+}
+
+/// The number of runs of a single solver in each state.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub struct SolverStatus {
+    /// runs with a stored result
+    pub done: usize,
+    /// runs whose result has been removed because they failed
+    pub failed: usize,
+    /// runs that have not been run yet
+    pub missing: usize,
+}
+
+/// The status of all solvers of a job, by solver id.
+pub type Status = BTreeMap<String, SolverStatus>;
+
+/// A run whose answer contradicts the result its benchmark is expected to have.
+#[derive(Serialize, Deserialize, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub struct SoundnessIssue {
+    pub solver: String,
+    pub benchmark: String,
+    pub repetition: usize,
+    pub expected: String,
+    pub answer: String,
+}
+
+impl fmt::Display for SoundnessIssue {
+    fn fmt(&self, w: &mut fmt::Formatter) -> fmt::Result {
+        write!(w, "{} answered {} on {}", self.solver, self.answer, self.benchmark)?;
+        if self.repetition != 0 {
+            write!(w, " (repetition {})", self.repetition)?;
+        }
+        write!(w, ", but {} is expected", self.expected)
+    }
 }
 
 #[derive(Serialize, Deserialize, Copy, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
 pub enum BenchmarkStatus {
     Success,
     Timeout,
+    /// the run was killed because it exceeded the memory limit
+    MemoryOut,
+    /// the run was killed because its output exceeded a size limit
+    OutputLimitExceeded,
 }
 
-#[derive(Serialize, Deserialize, Derivative)]
-#[derivative( Clone(bound=""), Debug(bound=""), Hash(bound=""), Ord(bound=""), PartialOrd(bound=""), Eq(bound=""), PartialEq(bound="") )]
+/// An output of a run that is subject to a size limit.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub enum CapturedOutput {
+    Stdout,
+    Stderr,
+    /// the files written to the working directory of the run
+    Files,
+}
+
+/// Resource usage of a solver process, as reported by the operating system once the process
+/// has terminated. Note that this includes all child processes the solver process has waited for.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub struct ResourceUsage {
+    pub user_time: Duration,
+    pub system_time: Duration,
+    /// maximum resident set size in bytes
+    pub max_rss: u64,
+    pub minor_page_faults: u64,
+    pub major_page_faults: u64,
+    pub voluntary_context_switches: u64,
+    pub involuntary_context_switches: u64,
+}
+
+impl ResourceUsage {
+    /// user plus system cpu time
+    pub fn cpu_time(&self) -> Duration {
+        self.user_time + self.system_time
+    }
+}
+
+/// Identifies the contents of an input file of a run.
+#[derive(Serialize, Deserialize, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub struct FileFingerprint {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    /// hex encoded sha256 hash of the file contents
+    pub sha256: String,
+}
+
+/// Fingerprints of the solver executable and the benchmark file a run has been computed with.
+/// They are `None` if the solver or benchmark is not backed by a file, or if the result has been
+/// stored before fingerprints were recorded.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub struct InputFingerprints {
+    pub solver: Option<FileFingerprint>,
+    pub benchmark: Option<FileFingerprint>,
+}
+
+/// An output of a run, i.e. its stdout, its stderr, or a file it has written. Outputs are not
+/// held in memory, but read from where they are stored whenever they are accessed.
+#[derive(Serialize, Deserialize, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub(crate) enum Output {
+    /// a file, either in the directory based result store, or in the temporary directory of a
+    /// run that has not been stored yet
+    File {
+        path: PathBuf,
+        #[serde(skip)]
+        tmp: KeepAlive,
+    },
+    /// a blob in the SQLite result store
+    Blob {
+        db: PathBuf,
+        blob: crate::dao::BlobRef,
+    },
+}
+
+/// Keeps the temporary directory of a run alive, as long as its outputs are referenced.
+#[derive(Clone, Default)]
+pub(crate) struct KeepAlive(pub(crate) Option<Arc<tempfile::TempDir>>);
+
+// a temporary directory is no part of the identity of an output
+impl PartialEq for KeepAlive { fn eq(&self, _: &Self) -> bool { true } }
+impl Eq for KeepAlive { }
+impl PartialOrd for KeepAlive { fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> { Some(self.cmp(other)) } }
+impl Ord for KeepAlive { fn cmp(&self, _: &Self) -> std::cmp::Ordering { std::cmp::Ordering::Equal } }
+impl std::hash::Hash for KeepAlive { fn hash<H: std::hash::Hasher>(&self, _: &mut H) { } }
+impl fmt::Debug for KeepAlive {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "KeepAlive") }
+}
+
+impl Output {
+    pub(crate) fn file(path: PathBuf, tmp: KeepAlive) -> Self {
+        Output::File { path, tmp }
+    }
+
+    pub(crate) fn open(&self) -> Result<Box<dyn io::Read>> {
+        Ok(match self {
+            Output::File { path, .. } => Box::new(io::BufReader::new(crate::dao::open_file(path)?)),
+            Output::Blob { db, blob } => Box::new(io::Cursor::new(crate::dao::read_blob(db, blob)?)),
+        })
+    }
+
+    /// The length of the output in bytes.
+    pub(crate) fn len(&self) -> Result<u64> {
+        Ok(match self {
+            Output::File { path, .. } => std::fs::metadata(path)
+                .with_context(|| format!("failed to read '{}'", path.display()))?
+                .len(),
+            Output::Blob { db, blob } => crate::dao::blob_len(db, blob)?,
+        })
+    }
+
+    // outputs are streamed where they are stored, they are only read as a whole by tests
+    #[cfg(test)]
+    pub(crate) fn read(&self) -> Result<Vec<u8>> {
+        use std::io::Read;
+        let mut bytes = vec![];
+        self.open()?.read_to_end(&mut bytes).context("failed to read output")?;
+        Ok(bytes)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
 pub(crate) struct FileConts {
+    /// path relative to the working directory of the run
     pub(crate) name: PathBuf,
-    pub(crate) bytes: Vec<u8>,
+    pub(crate) conts: Output,
 }
 
 #[derive(Serialize, Deserialize, Derivative)]
@@ -99,11 +288,23 @@ pub struct BenchRunResult<P>
     pub(crate) run: BenchRunConf<P>,
     pub(crate) status: BenchmarkStatus,
     pub(crate) time: Duration,
+    /// `None` for results that have been stored before resource usage was recorded
+    pub(crate) usage: Option<ResourceUsage>,
+    pub(crate) inputs: InputFingerprints,
     pub(crate) exit_status: Option<i32>,
+    /// outputs that exceeded their size limit, and have not been stored completely
+    pub(crate) truncated: Vec<CapturedOutput>,
+    /// the environment the solver has been run with, where redacted values are replaced. `None`
+    /// for results that have been stored before the environment was recorded
+    pub(crate) env: Option<BTreeMap<String, String>>,
+    /// the timeout of the job the result is reported for, if it has been obtained with another
+    /// timeout and is reused
+    #[serde(skip)]
+    pub(crate) requested_timeout: Option<Duration>,
     #[derivative(Debug="ignore")]
-    pub(crate) stdout: Vec<u8>,
+    pub(crate) stdout: Output,
     #[derivative(Debug="ignore")]
-    pub(crate) stderr: Vec<u8>,
+    pub(crate) stderr: Output,
     #[derivative(Debug="ignore")]
     pub(crate) files: Vec<FileConts>,
 }
@@ -128,13 +329,48 @@ impl<P> BenchRunResult<P>
     pub fn run(&self) -> &BenchRunConf<P> { &self.run }
     pub fn solver(&self) -> &P::Solver { &self.run().solver() }
     pub fn benchmark(&self) -> &P::Benchmark { &self.run().benchmark() }
-    pub fn stdout<'a>(&'a self) -> Result<impl io::Read + 'a> {
-        Ok(io::Cursor::new(&self.stdout))
+    pub fn repetition(&self) -> usize { self.run.repetition }
+    pub fn seed(&self) -> Option<u64> { self.run.seed }
+    /// The timeout of the job the result is reported for. It differs from the timeout of `run()`
+    /// if the result has been obtained with another timeout, and is reused.
+    pub fn requested_timeout(&self) -> Duration {
+        self.requested_timeout.unwrap_or(self.run.timeout)
+    }
+    /// Opens the stdout of the run, which is read from the result store.
+    pub fn stdout(&self) -> Result<impl io::Read> {
+        self.stdout.open()
+    }
+    /// Opens the stderr of the run, which is read from the result store.
+    pub fn stderr(&self) -> Result<impl io::Read> {
+        self.stderr.open()
+    }
+    /// Names of the files the run has written to its working directory, relative to it.
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|f| f.name.as_path())
     }
-    pub fn stderr<'a>(&'a self) -> Result<impl io::Read + 'a> {
-        Ok(io::Cursor::new(&self.stderr))
+    /// Opens the file `name` the run has written to its working directory.
+    pub fn file(&self, name: impl AsRef<Path>) -> Result<impl io::Read> {
+        let name = name.as_ref();
+        match self.files.iter().find(|f| f.name == name) {
+            Some(f) => f.conts.open(),
+            None => bail!("{} did not write a file {}", self.run, name.display()),
+        }
     }
     pub fn status(&self) -> Option<i32> {self.exit_status}
+    pub fn benchmark_status(&self) -> BenchmarkStatus {self.status}
+    /// wall clock time of the run
     pub fn time(&self) -> Duration {self.time}
+    pub fn usage(&self) -> Option<&ResourceUsage> {self.usage.as_ref()}
+    /// user plus system cpu time of the run
+    pub fn cpu_time(&self) -> Option<Duration> {self.usage.map(|u| u.cpu_time())}
+    /// maximum resident set size of the run in bytes
+    pub fn max_rss(&self) -> Option<u64> {self.usage.map(|u| u.max_rss)}
+    /// fingerprints of the solver and benchmark files used for the run
+    pub fn inputs(&self) -> &InputFingerprints {&self.inputs}
+    /// outputs that have been truncated, because they exceeded their size limit
+    pub fn truncated(&self) -> &[CapturedOutput] {&self.truncated}
+    /// the recorded environment variables the solver has been run with, if any have been
+    /// recorded. The values of variables matching `--redact-env` are `<redacted>`.
+    pub fn env(&self) -> Option<&BTreeMap<String, String>> {self.env.as_ref()}
     pub fn display_command(&self) -> impl fmt::Display { self.run.display_command() }
 }
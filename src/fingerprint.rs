@@ -0,0 +1,65 @@
+use anyhow::*;
+use sha2::{Digest, Sha256};
+use std::collections::*;
+use std::fs;
+use std::io;
+use std::path::*;
+use std::sync::Mutex;
+use std::result::Result::Ok;
+use crate::dto::FileFingerprint;
+
+/// Computes fingerprints of files. A file is hashed again only if its size or modification time
+/// differ from the ones of its cached fingerprint, e.g. because a solver has been rebuilt.
+#[derive(Default)]
+pub(crate) struct FingerprintCache {
+    cache: Mutex<BTreeMap<PathBuf, FileFingerprint>>,
+}
+
+impl FingerprintCache {
+
+    pub(crate) fn get(&self, path: &Path) -> Result<FileFingerprint> {
+        let meta = metadata(path)?;
+        if let Some(f) = self.cache.lock().unwrap().get(path) {
+            if unchanged(f, &meta) {
+                return Ok(f.clone());
+            }
+        }
+        let f = compute(path)?;
+        self.cache.lock().unwrap().insert(path.to_owned(), f.clone());
+        Ok(f)
+    }
+
+    /// Checks whether the file `path` still matches the fingerprint `old`. The file is only hashed
+    /// if its size or modification time differ from the ones recorded.
+    pub(crate) fn matches(&self, old: &FileFingerprint, path: &Path) -> Result<bool> {
+        if unchanged(old, &metadata(path)?) {
+            Ok(true)
+        } else {
+            Ok(self.get(path)?.sha256 == old.sha256)
+        }
+    }
+}
+
+fn metadata(path: &Path) -> Result<fs::Metadata> {
+    fs::metadata(path).with_context(|| format!("failed to read metadata of {}", path.display()))
+}
+
+/// Whether a file with the metadata `meta` has the size and modification time recorded in `f`.
+fn unchanged(f: &FileFingerprint, meta: &fs::Metadata) -> bool {
+    meta.len() == f.size && meta.modified().ok() == f.modified
+}
+
+fn compute(path: &Path) -> Result<FileFingerprint> {
+    let mut file = crate::dao::open_file(path)?;
+    let meta = file.metadata()
+        .with_context(|| format!("failed to read metadata of {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("failed to hash {}", path.display()))?;
+    Ok(FileFingerprint {
+        path: path.to_owned(),
+        size: meta.len(),
+        modified: meta.modified().ok(),
+        sha256: hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect(),
+    })
+}
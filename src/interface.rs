@@ -22,6 +22,27 @@ pub trait Benchmarker {
     type Mapped: Send + Sync;
     fn map(&self, r: &BenchRunResult<Self>) -> Result<Self::Mapped>;
 
+    /// Enables caching the mapped values in the result store, so that `map` is only invoked for
+    /// new or changed results. Disabled by default.
+    fn map_cache(&self) -> Option<MapCache<Self>> { None }
+
+    /// The answer the solver has reported in the result `r`, e.g. `sat` or `unsat`, which is
+    /// compared with the result the benchmark is expected to have (see `Benchmark::expected`).
+    /// Runs whose answer contradicts the expected one are reported as soundness issues.
+    fn answer(&self, r: &BenchRunResult<Self>, mapped: &Self::Mapped) -> Option<String> {
+        let _ = (r, mapped);
+        None
+    }
+
+    /// Whether the `answer` of a run contradicts the result its benchmark is `expected` to have.
+    /// By default they contradict if they differ ignoring case, unless one of them is empty or
+    /// `unknown`, i.e. no definite answer.
+    fn contradicts(&self, expected: &str, answer: &str) -> bool {
+        let definite = |s: &str| !s.is_empty() && !s.eq_ignore_ascii_case("unknown");
+        let (expected, answer) = (expected.trim(), answer.trim());
+        definite(expected) && definite(answer) && !expected.eq_ignore_ascii_case(answer)
+    }
+
     type Reduced: Serialize + DeserializeOwned + Summerizable + Sized;
     fn reduce(
         &self,
@@ -31,12 +52,45 @@ pub trait Benchmarker {
 
 }
 
+/// Describes how the values returned by `Benchmarker::map` are cached.
+pub struct MapCache<P>
+where P: Benchmarker + ?Sized
+{
+    pub(crate) version: String,
+    pub(crate) serialize: fn(&P::Mapped) -> serde_json::Result<serde_json::Value>,
+    pub(crate) deserialize: fn(serde_json::Value) -> serde_json::Result<P::Mapped>,
+}
+
+impl<P> MapCache<P>
+where P: Benchmarker + ?Sized,
+      P::Mapped: Serialize + DeserializeOwned,
+{
+    /// Cached values are only used if they have been computed by a mapper of the same `version`.
+    /// Hence it must be changed whenever `Benchmarker::map` changes.
+    pub fn new(version: impl Into<String>) -> Self {
+        MapCache {
+            version: version.into(),
+            serialize: |m| serde_json::to_value(m),
+            deserialize: serde_json::from_value,
+        }
+    }
+}
+
 pub trait Ident {
     type Id: std::fmt::Display;
     fn id(&self) -> &Self::Id;
 }
 
 pub trait Benchmark:  Ident + Clone + Debug+ Hash+ Ord+ PartialOrd + Eq + PartialEq + Serialize + DeserializeOwned + Sized + Send + Sync {
+    /// The file this benchmark is read from, if any. It is used to detect when a benchmark has
+    /// changed since results for it have been computed.
+    fn file(&self) -> Option<&Path> { None }
+    /// The result this benchmark is expected to have, if it is known.
+    fn expected(&self) -> Option<&str> { None }
+    /// The same benchmark, but read from `file` instead of `Benchmark::file`. It is used to pass a
+    /// decompressed copy of a compressed benchmark to the solver. `None` if the benchmark cannot
+    /// be read from another file.
+    fn with_file(&self, file: &Path) -> Option<Self> { let _ = file; None }
     // type Id: std::fmt::Display;
     // fn id(&self) -> &Self::Id;
     // fn to_command(&self, benchmark: &B, timeout: &Duration) -> std::process::Command;
@@ -48,6 +102,26 @@ pub trait Solver<P>: Ident + Clone + Debug+ Hash+ Ord+ PartialOrd + Eq + Partial
 {
     // type Id: std::fmt::Display;
     // fn id(&self) -> &Self::Id;
-    fn to_command(&self, benchmark: &P::Benchmark, timeout: &Duration) -> std::process::Command;
-    fn show_command(&self, benchmark: &P::Benchmark, timeout: &Duration) -> String;
+    /// `seed` is only present if the job consists of multiple repetitions. `outdir` is the
+    /// directory whose contents are stored as output files of the run. It is used as working
+    /// directory of the command, unless the command has its own working directory set.
+    fn to_command(&self, benchmark: &P::Benchmark, timeout: &Duration, seed: Option<u64>, outdir: &Path) -> std::process::Command;
+    fn show_command(&self, benchmark: &P::Benchmark, timeout: &Duration, seed: Option<u64>) -> String;
+    /// The executable of this solver, if any. It is used to detect when a solver has changed since
+    /// results for it have been computed.
+    fn executable(&self) -> Option<PathBuf> { None }
+    /// What the solver reads from stdin, if it determines this itself. Otherwise the `--stdin`
+    /// option decides.
+    fn stdin(&self) -> Option<StdinMode> { None }
+}
+
+/// What is connected to the stdin of a solver.
+#[derive(ValueEnum, Serialize, Deserialize, Copy, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StdinMode {
+    /// `/dev/null`, so that solvers cannot read from the runner's terminal
+    #[default]
+    Null,
+    /// the benchmark file, for solvers that read their problem from stdin
+    Benchmark,
 }
@@ -1,12 +1,41 @@
 use super::*;
+use std::collections::BTreeMap;
 use std::fmt;
+use std::fs;
+use serde_json::{Map, Value};
 
+/// A file, identified by its path relative to the directory it has been discovered in.
 #[derive(Serialize, Deserialize, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
-pub struct PathId(PathBuf);
+#[serde(from = "PathIdRepr")]
+pub struct PathId {
+    file: PathBuf,
+    id: PathBuf,
+}
+
+/// Results stored before benchmarks were discovered recursively only contain the file, which
+/// used to be identified by its file name.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PathIdRepr {
+    File(PathBuf),
+    PathId { file: PathBuf, id: PathBuf },
+}
+
+impl From<PathIdRepr> for PathId {
+    fn from(repr: PathIdRepr) -> Self {
+        match repr {
+            PathIdRepr::File(file) => PathId {
+                id: file.file_name().map(PathBuf::from).unwrap_or_else(|| file.clone()),
+                file,
+            },
+            PathIdRepr::PathId { file, id } => PathId { file, id },
+        }
+    }
+}
 
 impl AsRef<PathBuf> for PathId {
     fn as_ref(&self)-> &PathBuf {
-        &self.0
+        &self.file
     }
 }
 
@@ -18,16 +47,156 @@ impl Ident for PathId {
 }
 
 
-impl Benchmark for PathId { }
+impl Benchmark for PathId {
+    fn file(&self) -> Option<&Path> { Some(&self.file) }
+    fn with_file(&self, file: &Path) -> Option<Self> {
+        Some(PathId { file: file.to_owned(), id: self.id.clone() })
+    }
+}
 
 impl FromDir for PathId {
     fn from_dir<P: AsRef<Path>>(file: P) -> Result<PathId> {
-        Ok(PathId(file.as_ref().canonicalize().context("failed to canonicalize")?.into()))
+        let file: PathBuf = file.as_ref().canonicalize().context("failed to canonicalize")?;
+        let id = file.file_name().map(PathBuf::from).unwrap_or_else(|| file.clone());
+        Ok(PathId { file, id })
+    }
+}
+
+impl FromBenchFile for PathId {
+    fn from_bench_file(file: &Path, id: &Path) -> Result<PathId> {
+        let file = file.canonicalize()
+            .with_context(|| format!("failed to canonicalize {}", file.display()))?;
+        Ok(PathId { file, id: id.to_owned() })
     }
 }
 
 impl fmt::Display for PathId {
     fn fmt(&self, w: &mut fmt::Formatter) -> fmt::Result {
-        write!(w, "{}", self.0.display())
+        write!(w, "{}", self.id.display())
+    }
+}
+
+/// Metadata of a benchmark, as provided by benchmark suites.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub struct BenchMeta {
+    /// the expected result, e.g. `sat`, `unsat`, or `Theorem`
+    pub expected: Option<String>,
+    /// the difficulty rating of the benchmark
+    pub difficulty: Option<String>,
+    /// any further fields, rendered as strings
+    pub other: BTreeMap<String, String>,
+}
+
+impl BenchMeta {
+    /// Reads the metadata from the fields of a JSON object. The expected result is read from the
+    /// field `expected` or `status`, the difficulty from `difficulty` or `rating`.
+    pub fn from_json(fields: &Map<String, Value>) -> BenchMeta {
+        let mut meta = BenchMeta::default();
+        for (key, value) in fields {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                Value::Null => continue,
+                other => other.to_string(),
+            };
+            match key.as_str() {
+                "expected" | "status" => meta.expected = Some(value),
+                "difficulty" | "rating" => meta.difficulty = Some(value),
+                _ => { meta.other.insert(key.clone(), value); }
+            }
+        }
+        meta
+    }
+
+    /// The fields of `other` override the ones of `self`.
+    fn merge(self, other: BenchMeta) -> BenchMeta {
+        let mut fields = self.other;
+        fields.extend(other.other);
+        BenchMeta {
+            expected: other.expected.or(self.expected),
+            difficulty: other.difficulty.or(self.difficulty),
+            other: fields,
+        }
+    }
+}
+
+/// A benchmark file annotated with metadata. The metadata is read from the sidecar file
+/// `<file>.meta.json` if it exists, a JSON object whose fields are read as by
+/// [`BenchMeta::from_json`], and from the benchmark's entry in a JSON manifest, which takes
+/// precedence.
+#[derive(Serialize, Deserialize, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub struct AnnotatedPath {
+    path: PathId,
+    meta: BenchMeta,
+}
+
+impl AnnotatedPath {
+    pub fn path(&self) -> &PathId { &self.path }
+    pub fn meta(&self) -> &BenchMeta { &self.meta }
+    /// the difficulty rating, if it is a number
+    pub fn difficulty(&self) -> Option<f64> {
+        self.meta.difficulty.as_ref().and_then(|d| d.parse().ok())
+    }
+}
+
+impl AsRef<PathBuf> for AnnotatedPath {
+    fn as_ref(&self)-> &PathBuf {
+        self.path.as_ref()
+    }
+}
+
+impl Ident for AnnotatedPath {
+    type Id = PathId;
+    fn id(&self) -> &Self::Id {
+        &self.path
+    }
+}
+
+impl Benchmark for AnnotatedPath {
+    fn file(&self) -> Option<&Path> { self.path.file() }
+    fn expected(&self) -> Option<&str> { self.meta.expected.as_deref() }
+    fn with_file(&self, file: &Path) -> Option<Self> {
+        Some(AnnotatedPath { path: self.path.with_file(file)?, meta: self.meta.clone() })
+    }
+}
+
+/// Files ending with this suffix are sidecar files, rather than benchmarks.
+pub(crate) const SIDECAR_SUFFIX: &str = ".meta.json";
+
+/// The sidecar file containing the metadata of the benchmark `file`.
+fn sidecar_file(file: &Path) -> PathBuf {
+    let mut name = file.as_os_str().to_owned();
+    name.push(SIDECAR_SUFFIX);
+    PathBuf::from(name)
+}
+
+fn read_sidecar(file: &Path) -> Result<BenchMeta> {
+    let sidecar = sidecar_file(file);
+    if !sidecar.exists() {
+        return Ok(BenchMeta::default());
+    }
+    let conts = fs::read_to_string(&sidecar)
+        .with_context(|| format!("failed to read {}", sidecar.display()))?;
+    let fields: Map<String, Value> = serde_json::from_str(&conts)
+        .with_context(|| format!("failed to parse {}", sidecar.display()))?;
+    Ok(BenchMeta::from_json(&fields))
+}
+
+impl FromBenchFile for AnnotatedPath {
+    fn from_bench_file(file: &Path, id: &Path) -> Result<AnnotatedPath> {
+        Ok(AnnotatedPath {
+            path: PathId::from_bench_file(file, id)?,
+            meta: read_sidecar(file)?,
+        })
+    }
+
+    fn from_manifest_entry(file: &Path, id: &Path, meta: &Map<String, Value>) -> Result<AnnotatedPath> {
+        let AnnotatedPath { path, meta: sidecar } = AnnotatedPath::from_bench_file(file, id)?;
+        Ok(AnnotatedPath { path, meta: sidecar.merge(BenchMeta::from_json(meta)) })
+    }
+}
+
+impl fmt::Display for AnnotatedPath {
+    fn fmt(&self, w: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.path, w)
     }
 }
@@ -7,6 +7,8 @@ use serde::*;
 use anyhow::Result;
 use std::process::*;
 use crate::interface::ids::*;
+use std::collections::*;
+use std::sync::Arc;
 
 #[derive(Serialize, Deserialize, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
 pub struct Script {
@@ -17,12 +19,14 @@ pub struct Script {
 enum Args<'a> {
     PathBuf(&'a PathBuf),
     TimeOut(String),
+    Seed(String),
 }
 impl<'a> AsRef<OsStr> for Args<'a> {
     fn as_ref(&self) -> &OsStr {
         match self {
             Args::PathBuf(p) => p.as_ref(),
             Args::TimeOut(t) => t.as_ref(),
+            Args::Seed(s) => s.as_ref(),
         }
     }
 }
@@ -31,6 +35,7 @@ impl<'a> fmt::Display for Args<'a> {
         match self {
             Args::PathBuf(p) => p.display().fmt(w),
             Args::TimeOut(t) => t.fmt(w),
+            Args::Seed(s) => s.fmt(w),
         }
     }
 }
@@ -54,22 +59,27 @@ impl Ident for Script {
 }
 
 impl<P> Solver<P> for Script 
-    where P: Benchmarker<Benchmark=PathId>,
+    where P: Benchmarker,
+          P::Benchmark: AsRef<PathBuf>,
 {
-    fn to_command(&self, benchmark: &PathId, timeout: &Duration) -> std::process::Command {
+    fn to_command(&self, benchmark: &P::Benchmark, timeout: &Duration, seed: Option<u64>, _outdir: &Path) -> std::process::Command {
         let mut cmd = Command::new(self.command().as_ref());
-        cmd.args(self.args(benchmark, &timeout));
+        cmd.args(self.args(benchmark, &timeout, seed));
         cmd
     }
 
-    fn show_command(&self, benchmark: &PathId, timeout: &Duration) -> String {
+    fn show_command(&self, benchmark: &P::Benchmark, timeout: &Duration, seed: Option<u64>) -> String {
         let mut w = self.command().as_ref().display().to_string();
-        for arg in self.args(benchmark, timeout) {
+        for arg in self.args(benchmark, timeout, seed) {
             w.push(' ');
             w.push_str(&arg.to_string());
         }
         w
     }
+
+    fn executable(&self) -> Option<PathBuf> {
+        Some(self.command().as_ref().clone())
+    }
 }
 
 impl Script {
@@ -78,10 +88,11 @@ impl Script {
         &self.file
     }
 
-    pub fn args<'a>(&'a self, benchmark: &'a PathId, timeout: &'a Duration) -> impl IntoIterator<Item = impl AsRef<OsStr> + fmt::Display + 'a> + 'a {
+    pub fn args<'a, B: AsRef<PathBuf>>(&'a self, benchmark: &'a B, timeout: &'a Duration, seed: Option<u64>) -> impl IntoIterator<Item = impl AsRef<OsStr> + fmt::Display + 'a> + 'a {
         use std::iter::once;
         once(Args::PathBuf(&benchmark.as_ref()))
             .chain(once(Args::TimeOut(format!("{}", timeout.as_secs()))))
+            .chain(seed.map(|s| Args::Seed(format!("{}", s))))
     }
 
 
@@ -100,3 +111,286 @@ impl FromDir for Script {
 
     }
 }
+
+
+/// A solver defined by an entry of a solver manifest file.
+///
+/// A manifest is either a TOML file (with extension `.toml`), or a JSON file, containing a list of
+/// solvers. E.g.:
+/// ```toml
+/// [[solvers]]
+/// id = "z3-default"
+/// command = "/usr/bin/z3"
+/// args = ["-T:{timeout}", "{benchmark}"]
+/// env = { OMP_NUM_THREADS = "1" }
+/// ```
+///
+/// The arguments may contain the placeholders `{benchmark}`, `{timeout}` (in seconds), `{seed}`
+/// (`0` if no repetitions are requested), and `{outdir}`, the directory whose contents are stored
+/// as the output files of the run. By default the solver is run within `{outdir}`, unless a
+/// `working_dir` is given. Relative paths for `command` and `working_dir` are resolved relative
+/// to the manifest file. If `args` is not given the solver is invoked as
+/// `<command> <benchmark> <timeout>`.
+///
+/// A manifest may further contain parameter grids, which are expanded into one solver for each
+/// combination of parameter values. The parameters can be used as placeholders in `args` and
+/// `env`, and the id of each expanded solver is derived from the grid's id and its parameter
+/// values. E.g. the following defines the solvers `vampire--preprocess=off--strategy=a`,
+/// `vampire--preprocess=on--strategy=a`, `vampire--preprocess=off--strategy=b`, etc.:
+/// ```toml
+/// [[grids]]
+/// id = "vampire"
+/// command = "vampire"
+/// args = ["--strategy", "{strategy}", "--preprocess", "{preprocess}", "{benchmark}"]
+/// grid = { strategy = ["a", "b", "c"], preprocess = ["on", "off"] }
+/// ```
+/// Since the ids only depend on the parameter values of the individual solver, adding values to a
+/// grid does not change the ids of the solvers that have been defined before.
+///
+/// Solvers that read their problem from stdin are given `stdin = "benchmark"`, which connects the
+/// benchmark file to their stdin, and `stdin = "null"` connects it to `/dev/null`. Solvers that do
+/// not set `stdin` follow the `--stdin` option.
+#[derive(Serialize, Deserialize, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub struct Configured {
+    pub(crate) id: String,
+    pub(crate) command: PathBuf,
+    #[serde(default = "default_args")]
+    pub(crate) args: Vec<String>,
+    #[serde(default)]
+    pub(crate) env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub(crate) working_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub(crate) stdin: Option<StdinMode>,
+    /// the parameter values, if this solver stems from a parameter grid
+    #[serde(default)]
+    pub(crate) params: BTreeMap<String, String>,
+}
+
+fn default_args() -> Vec<String> {
+    vec!["{benchmark}".to_owned(), "{timeout}".to_owned()]
+}
+
+#[derive(Deserialize)]
+struct SolverManifest {
+    #[serde(default)]
+    solvers: Vec<Configured>,
+    #[serde(default)]
+    grids: Vec<Grid>,
+}
+
+#[derive(Deserialize)]
+struct Grid {
+    #[serde(flatten)]
+    solver: Configured,
+    grid: BTreeMap<String, Vec<String>>,
+}
+
+impl Grid {
+    fn expand(self) -> Result<Vec<Configured>> {
+        let Grid { solver, grid } = self;
+        for (param, values) in &grid {
+            if PLACEHOLDERS.contains(&&param[..]) {
+                bail!("grid {}: parameter {} clashes with a builtin placeholder", solver.id, param);
+            }
+            if values.is_empty() {
+                bail!("grid {}: no values given for parameter {}", solver.id, param);
+            }
+            if let Some(v) = values.iter().find(|v| v.contains('/')) {
+                bail!("grid {}: parameter value {} must not contain '/'", solver.id, v);
+            }
+        }
+        let params = grid.iter()
+            .map(|(param, values)| values.iter().map(move |v| (param.clone(), v.clone())))
+            .multi_cartesian_product();
+        // multi_cartesian_product yields nothing for an empty grid
+        let params: Vec<BTreeMap<_, _>> = if grid.is_empty() {
+            vec![BTreeMap::new()]
+        } else {
+            params.map(|ps| ps.into_iter().collect()).collect()
+        };
+        Ok(params.into_iter()
+            .map(|params| {
+                let subst = |s: &String| params.iter()
+                    .fold(s.clone(), |s, (p, v)| s.replace(&format!("{{{}}}", p), v));
+                Configured {
+                    id: params.iter()
+                        .fold(solver.id.clone(), |id, (p, v)| format!("{}--{}={}", id, p, v)),
+                    command: solver.command.clone(),
+                    args: solver.args.iter().map(subst).collect(),
+                    env: solver.env.iter().map(|(k, v)| (k.clone(), subst(v))).collect(),
+                    working_dir: solver.working_dir.clone(),
+                    stdin: solver.stdin,
+                    params,
+                }
+            })
+            .collect())
+    }
+}
+
+const PLACEHOLDERS: &[&str] = &["benchmark", "timeout", "seed", "outdir"];
+
+impl Configured {
+
+    /// Reads all solvers defined in the manifest `file`.
+    pub fn read_manifest(file: impl AsRef<Path>) -> Result<Vec<Self>> {
+        let file = file.as_ref();
+        let manifest: SolverManifest = if file.extension() == Some(OsStr::new("toml")) {
+            let conts = std::fs::read_to_string(file)
+                .with_context(|| format!("failed to read solver manifest {}", file.display()))?;
+            toml::from_str(&conts)
+                .with_context(|| format!("failed to parse solver manifest {}", file.display()))?
+        } else {
+            crate::dao::read_json(file)?
+        };
+        // solvers are run within another directory, hence relative paths are resolved to
+        // absolute ones
+        let base = match file.parent() {
+            Some(dir) if dir != Path::new("") => dir,
+            _ => Path::new("."),
+        };
+        let base = base.canonicalize()
+            .with_context(|| format!("failed to canonicalize the directory of solver manifest {}", file.display()))?;
+        let grids = manifest.grids
+            .into_iter()
+            .map(Grid::expand)
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("invalid solver manifest {}", file.display()))?;
+        manifest.solvers
+            .into_iter()
+            .chain(grids.into_iter().flatten())
+            .map(|s| s.resolve(&base))
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("invalid solver manifest {}", file.display()))
+    }
+
+    fn resolve(mut self, base: &Path) -> Result<Self> {
+        for arg in &self.args {
+            for p in placeholders(arg) {
+                if !PLACEHOLDERS.contains(&p) {
+                    bail!("solver {}: unknown placeholder {{{}}} (expected one of {})", self.id, p, PLACEHOLDERS.iter().map(|p| format!("{{{}}}", p)).join(", "));
+                }
+            }
+        }
+        // commands without a path component are looked up in $PATH
+        if self.command.components().count() > 1 {
+            self.command = base.join(&self.command);
+        }
+        self.working_dir = self.working_dir.map(|d| base.join(d));
+        Ok(self)
+    }
+
+    pub fn command(&self) -> &Path {
+        &self.command
+    }
+
+    pub fn env(&self) -> &BTreeMap<String, String> {
+        &self.env
+    }
+
+    pub fn working_dir(&self) -> Option<&Path> {
+        self.working_dir.as_deref()
+    }
+
+    pub fn stdin(&self) -> Option<StdinMode> {
+        self.stdin
+    }
+
+    /// The parameter values of this solver, if it stems from a parameter grid.
+    pub fn params(&self) -> &BTreeMap<String, String> {
+        &self.params
+    }
+
+    pub fn args<B: AsRef<PathBuf>>(&self, benchmark: &B, timeout: &Duration, seed: Option<u64>, outdir: &str) -> Vec<String> {
+        self.args.iter()
+            .map(|arg| arg
+                .replace("{benchmark}", &benchmark.as_ref().display().to_string())
+                .replace("{timeout}", &timeout.as_secs().to_string())
+                .replace("{seed}", &seed.unwrap_or(0).to_string())
+                .replace("{outdir}", outdir))
+            .collect()
+    }
+}
+
+/// the names of all placeholders `{name}` occurring in `arg`
+fn placeholders(arg: &str) -> impl Iterator<Item=&str> {
+    arg.split('{').skip(1).filter_map(|s| s.split('}').next())
+}
+
+impl Ident for Configured {
+    type Id = String;
+    fn id(&self) -> &Self::Id {
+        &self.id
+    }
+}
+
+impl<P> Solver<P> for Configured
+    where P: Benchmarker,
+          P::Benchmark: AsRef<PathBuf>,
+{
+    fn to_command(&self, benchmark: &P::Benchmark, timeout: &Duration, seed: Option<u64>, outdir: &Path) -> std::process::Command {
+        let mut cmd = Command::new(&self.command);
+        cmd.args(self.args(benchmark, timeout, seed, &outdir.display().to_string()));
+        cmd.envs(&self.env);
+        if let Some(dir) = &self.working_dir {
+            cmd.current_dir(dir);
+        }
+        cmd
+    }
+
+    fn show_command(&self, benchmark: &P::Benchmark, timeout: &Duration, seed: Option<u64>) -> String {
+        let mut w = String::new();
+        for (k, v) in &self.env {
+            w.push_str(&format!("{}={} ", k, v));
+        }
+        w.push_str(&self.command.display().to_string());
+        for arg in self.args(benchmark, timeout, seed, "{outdir}") {
+            w.push(' ');
+            w.push_str(&arg);
+        }
+        if self.stdin == Some(StdinMode::Benchmark) {
+            w.push_str(&format!(" < {}", benchmark.as_ref().display()));
+        }
+        w
+    }
+
+    fn executable(&self) -> Option<PathBuf> {
+        if self.command.components().count() > 1 {
+            Some(self.command.clone())
+        } else {
+            std::env::var_os("PATH")
+                .and_then(|path| std::env::split_paths(&path)
+                    .map(|dir| dir.join(&self.command))
+                    .find(|f| f.is_file()))
+        }
+    }
+
+    fn stdin(&self) -> Option<StdinMode> {
+        self.stdin
+    }
+}
+
+impl FromDir for Vec<Arc<Configured>> {
+    /// Reads the solvers from a manifest file, or from all manifest files in a directory.
+    fn from_dir<P>(path: P) -> Result<Self>
+        where P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let solvers = if path.is_dir() {
+            let mut solvers = Vec::new();
+            for f in read_dir(&path)? {
+                solvers.extend(Configured::read_manifest(f?.path())?);
+            }
+            solvers
+        } else {
+            Configured::read_manifest(path)?
+        };
+        let mut ids = BTreeSet::new();
+        for s in &solvers {
+            if !ids.insert(&s.id) {
+                bail!("solver id {} is defined multiple times", s.id);
+            }
+        }
+        Ok(solvers.into_iter().map(Arc::new).collect())
+    }
+}
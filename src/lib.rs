@@ -13,11 +13,17 @@ mod interface;
 mod dto;
 mod ui;
 mod service;
+mod process;
+mod fingerprint;
+mod report;
+mod discovery;
+mod compression;
 
 pub use interface::*;
 pub use ui::*;
 pub use dto::*;
 pub use dao::DaoConfig;
+pub use discovery::{BenchmarkFilter, FromBenchDir, FromBenchFile};
 use service::*;
 
 use anyhow::Result;
@@ -33,10 +39,10 @@ use std::fs::DirEntry;
 use std::io;
 use std::path::*;
 use std::sync::*;
+use std::collections::BTreeMap;
 use std::time::*;
 use clap::*;
 use thiserror::Error as ThisError;
-use wait_timeout::ChildExt;
 use std::convert::*;
 pub use dao::*;
 pub use dao::read_dir;
@@ -69,17 +75,58 @@ mod test;
 /// Gets a set of solvers and a set of benchmarks as inputs and runs each solver on each benchmark.
 /// Each solver will be invoked as its own process using
 ///
-/// $ <solver> <benchmark> <timeout>
+/// $ <solver> <benchmark> <timeout> [<seed>]
+///
+/// where the seed is only passed if multiple repetitions are requested. Each repetition gets a
+/// different seed.
 ///
 /// stdout, and stderr of the process will be captured and written to the output directory.
 ///
 /// The solver shall indicate success with a return value of zero and failure with a non-zero
 /// return value. If the solver returns non-zero its stdout, and stderr will be moved to a
 /// the output directory in a subdirectory suffixed by `.err`. These *.err directories may be
-/// deleted whe the benchmark runner is invoked with the same output directory again.
+/// deleted whe the benchmark runner is invoked with the same output directory again, or
+/// explicitly using the `clean` subcommand.
+///
+/// Finally the results are postprocessed, and the postprocessed result is stored together with
+/// its summary in a report directory `<outdir>/reports/<timestamp>`.
+pub enum Cli {
+    /// Run each solver on each benchmark, unless there is a result already, and postprocess all
+    /// results.
+    Run(Opts),
+    /// Print how many runs are done, failed, or missing per solver, without running anything.
+    Status(Opts),
+    /// Postprocess the existing results, without running anything. The same as `run --post`.
+    Report(Opts),
+    /// Delete failed and incompletely stored results, and results of solvers or benchmarks that
+    /// are no longer present. Results of runs claimed by a running instance are kept.
+    Clean(Opts),
+    /// Import all results of a directory based result tree (i.e. an output directory of the
+    /// `files` store) into the result store. Results that are stored already are kept.
+    Import(ImportOpts),
+    /// Move results stored by earlier versions, which used to store the results of all
+    /// benchmarks with the same file name at the same location, to their current location.
+    Migrate(Opts),
+    /// Print the command, status, time, and output of the stored results of a single solver on a
+    /// single benchmark.
+    Show(ShowOpts),
+    /// Print the summary of a report that has been stored before again, without touching any
+    /// results.
+    Render {
+        /// the report directory, e.g. `<outdir>/reports/latest`
+        #[clap(parse(from_os_str))]
+        report: PathBuf,
+    },
+}
+
+/// Options shared by all subcommands, describing the job and where its results are stored.
+#[derive(Args, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Opts {
     /// directory that must containn must contain poroblem instance files, that will be passed to
-    /// the solver as first argument.
+    /// the solver as first argument. Instead of a directory this may be a file listing the
+    /// benchmarks, one path per line (lines starting with `#` are comments), or a JSON manifest
+    /// (`*.json`), i.e. an array whose entries are either a path, or an object with a `path` and
+    /// further metadata of the benchmark. Paths are relative to the file's directory.
     #[clap(
         parse(from_os_str),
         short = 'b',
@@ -88,7 +135,31 @@ pub struct Opts {
     )]
     pub bench_dir: PathBuf,
 
-    /// Directory containing solvers.
+    /// Search the benchmark directory recursively. Otherwise only the files directly in it are
+    /// benchmarks. Results of nested benchmarks are stored under their relative path. Symlinked
+    /// directories are followed, a directory reached via several paths is only searched once.
+    #[clap(short = 'R', long = "recursive")]
+    pub recursive: bool,
+
+    /// Only use benchmarks matching one of these glob patterns (may be given multiple times).
+    /// Patterns containing a `/` are matched against the path relative to the benchmark
+    /// directory, where `*` does not match a `/`, but `**` does. Other patterns are matched
+    /// against the file name.
+    #[clap(long = "include")]
+    pub include: Vec<String>,
+
+    /// Skip benchmarks matching one of these glob patterns (may be given multiple times), e.g.
+    /// `README*`. Patterns are matched like the ones of `--include`.
+    #[clap(long = "exclude")]
+    pub exclude: Vec<String>,
+
+    /// Only use benchmarks with one of these file extensions (may be given multiple times), e.g.
+    /// `smt2` or `p.gz`.
+    #[clap(long = "ext")]
+    pub extensions: Vec<String>,
+
+    /// Directory containing solvers. Depending on the solver type this may also be a solver
+    /// manifest file.
     #[clap(
         parse(from_os_str),
         short = 's',
@@ -100,6 +171,75 @@ pub struct Opts {
     /// timeout in seconds
     pub timeout: u64,
 
+    /// Memory limit in megabytes. The resident memory of each solver process (including all its
+    /// child processes) is monitored and the run is killed and recorded as `MemoryOut` when it
+    /// exceeds this limit. Results obtained with another memory limit are treated like results
+    /// whose inputs changed.
+    #[clap(short = 'm', long = "memory-limit")]
+    pub memory_limit: Option<u64>,
+
+    /// Limit of the stdout of each run in megabytes.
+    #[clap(long = "stdout-limit")]
+    pub stdout_limit: Option<u64>,
+
+    /// Limit of the stderr of each run in megabytes.
+    #[clap(long = "stderr-limit")]
+    pub stderr_limit: Option<u64>,
+
+    /// Limit of the total size of the files each run writes to its working directory in
+    /// megabytes. A run exceeding it is always killed and recorded as `OutputLimitExceeded`, and
+    /// its files are not stored.
+    #[clap(long = "files-limit")]
+    pub files_limit: Option<u64>,
+
+    /// What to do with a run whose stdout or stderr exceeds its limit. In either case only the
+    /// output up to the limit is stored, followed by a marker, and the truncation is recorded in
+    /// the result.
+    #[clap(long = "on-output-limit", value_enum, default_value = "kill")]
+    pub on_output_limit: OnOutputLimit,
+
+    /// Pass benchmarks compressed with gzip (`.gz`), xz (`.xz`), bzip2 (`.bz2`) or zstd (`.zst`)
+    /// to the solver as a decompressed copy, which is created in the run's temporary directory.
+    /// Results are still identified by the compressed benchmark.
+    #[clap(long = "decompress")]
+    pub decompress: bool,
+
+    /// What to connect to the stdin of solvers: `null` connects `/dev/null`, so that solvers
+    /// cannot read from the runner's terminal, and `benchmark` connects the benchmark file, for
+    /// solvers that read their problem from stdin. Solvers defined in a manifest may set this
+    /// themselves.
+    #[clap(long = "stdin", value_enum, default_value = "null")]
+    pub stdin: StdinMode,
+
+    /// Run solvers in a clean environment, that only contains the variables passed with
+    /// `--keep-env` and `--env`, and the ones set by the solver itself. By default solvers inherit
+    /// the runner's environment. The full environment of each run is recorded with its result,
+    /// see `--redact-env` to keep secrets out of it.
+    #[clap(long = "clean-env")]
+    pub clean_env: bool,
+
+    /// Variable of the runner's environment to pass to solvers in a clean environment, e.g.
+    /// `PATH`. May be given multiple times.
+    #[clap(long = "keep-env", requires = "clean-env")]
+    pub keep_env: Vec<String>,
+
+    /// Environment variable `NAME=VALUE` to set for all solvers. May be given multiple times.
+    /// Variables set by a solver itself, e.g. in a solver manifest, take precedence.
+    #[clap(long = "env")]
+    pub env: Vec<String>,
+
+    /// Glob pattern of environment variables whose values are not recorded with the results, e.g.
+    /// `'*_TOKEN'`. The variables are still passed to solvers, but recorded as `<redacted>`. May be
+    /// given multiple times.
+    #[clap(long = "redact-env")]
+    pub redact_env: Vec<String>,
+
+    /// How often each solver shall be run on each benchmark. The first repetition is stored like
+    /// a run that is not repeated, the others next to it (suffixed by `.rep<n>` in the `files`
+    /// store). Hence existing results are kept when the number of repetitions is increased.
+    #[clap(short = 'r', long = "repetitions", default_value = "1")]
+    pub repetitions: usize,
+
     /// directory to which the outputs written
     #[clap(
         parse(from_os_str),
@@ -109,19 +249,130 @@ pub struct Opts {
     )]
     pub outdir: PathBuf,
 
-    // /// only run post processor, not benchmarks
-    // #[clap(short = 'p', long = "post")]
-    // pub only_post_process: bool,
+    /// Only postprocess the results that are stored already. Missing runs are reported, but
+    /// solvers are never launched. Results whose inputs changed are used with a warning instead
+    /// of being rerun.
+    #[clap(short = 'p', long = "post")]
+    pub only_post_process: bool,
 
     /// How many threads shall be ran in parallel? [default: number of physical cpus]
     #[clap(short = 't', long = "threads")]
     pub num_threads: Option<usize>,
+
+    /// Time in milliseconds solvers are given to shut down gracefully when they are terminated
+    /// (e.g. on timeout). The solver's process group is first sent SIGTERM, and then SIGKILL
+    /// after this grace period.
+    #[clap(long = "grace-period", default_value = "1000")]
+    pub grace_period: u64,
+
+    /// What to do with existing results whose solver executable or benchmark file has changed
+    /// since they have been computed, or that have been computed with another memory limit or
+    /// in another recorded environment.
+    #[clap(long = "on-changed-inputs", value_enum, default_value = "rerun")]
+    pub on_changed_inputs: ChangedInputs,
+
+    /// Reuse results that have been obtained with a different timeout, if they are still valid:
+    /// A successful run with a smaller timeout is reused for a larger timeout, and a run that
+    /// timed out with a larger timeout is reused (as timeout) for a smaller timeout.
+    #[clap(long = "reuse-timeouts")]
+    pub reuse_timeouts: bool,
+
+    /// How results are stored in the output directory.
+    #[clap(long = "store", value_enum, default_value = "files")]
+    pub store: DaoBackend,
+}
+
+#[derive(Args, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ImportOpts {
+    /// output directory whose result store the results are imported into
+    #[clap(
+        parse(from_os_str),
+        short = 'o',
+        long = "outdir",
+        default_value = "benchmark_results"
+    )]
+    pub outdir: PathBuf,
+
+    /// How results are stored in the output directory.
+    #[clap(long = "store", value_enum, default_value = "files")]
+    pub store: DaoBackend,
+
+    /// the directory based result tree to import
+    #[clap(parse(from_os_str))]
+    pub from: PathBuf,
+}
+
+#[derive(Args, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ShowOpts {
+    #[clap(flatten)]
+    pub opts: Opts,
+    /// id or file name of the solver
+    pub solver: String,
+    /// id or file name of the benchmark
+    pub benchmark: String,
+}
+
+/// What to do with an existing result, if the solver or the benchmark it has been computed with
+/// has changed since, or if it has been computed with another memory limit or in another recorded
+/// environment.
+#[derive(ValueEnum, Copy, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ChangedInputs {
+    /// discard the result and run the benchmark again
+    Rerun,
+    /// keep the result, but print a warning
+    Warn,
+    /// keep the result silently
+    Keep,
+}
+
+/// What to do with a run whose stdout or stderr exceeds its size limit.
+#[derive(ValueEnum, Copy, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq, Serialize, Deserialize)]
+pub enum OnOutputLimit {
+    /// kill the run and record it as `OutputLimitExceeded`
+    Kill,
+    /// let the run finish, but discard the output beyond the limit
+    Truncate,
+}
+
+/// Size limits of the outputs of each run in bytes.
+#[derive(Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub struct OutputLimits {
+    pub stdout: Option<u64>,
+    pub stderr: Option<u64>,
+    /// total size of the files written to the working directory
+    pub files: Option<u64>,
+    pub on_exceeded: OnOutputLimit,
+}
+
+/// The environment solvers are run with.
+#[derive(Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub struct EnvConfig {
+    /// only pass the variables in `keep` from the runner's environment
+    pub clean: bool,
+    pub keep: Vec<String>,
+    /// variables set for all solvers
+    pub set: BTreeMap<String, String>,
+    /// variables whose values are not recorded
+    pub redact: Vec<glob::Pattern>,
 }
 
 //TODO create sercice module
 #[derive(Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
 pub struct ServiceConfig {
     pub threads: Option<usize>,
+    /// time between sending SIGTERM and SIGKILL to a solver that is terminated
+    pub grace_period: Duration,
+    pub on_changed_inputs: ChangedInputs,
+    /// reuse results that have been obtained with other timeouts, where possible
+    pub reuse_timeouts: bool,
+    /// only postprocess existing results, without running missing ones
+    pub only_post_process: bool,
+    pub output_limits: OutputLimits,
+    /// pass compressed benchmarks to the solver as a decompressed copy
+    pub decompress: bool,
+    /// what to connect to the stdin of solvers that do not determine this themselves
+    pub stdin: StdinMode,
+    pub env: EnvConfig,
 }
 
 #[derive(Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
@@ -138,25 +389,98 @@ fn validate_opts<P: Benchmarker>(
     opts: Opts,
 ) -> Result<ApplicationConfig<P>> 
     where P: Benchmarker,
-          P::Solver: FromDir,
-          P::Benchmark: FromDir,
+          Vec<Arc<P::Solver>>: FromDir,
+          Vec<Arc<P::Benchmark>>: FromBenchDir,
 {
     let Opts {
         bench_dir,
+        recursive,
+        include,
+        exclude,
+        extensions,
         solver_dir,
         outdir,
+        only_post_process,
         num_threads: threads,
         timeout,
+        memory_limit,
+        stdout_limit,
+        stderr_limit,
+        files_limit,
+        on_output_limit,
+        decompress,
+        stdin,
+        clean_env,
+        keep_env,
+        redact_env,
+        env,
+        repetitions,
+        grace_period,
+        on_changed_inputs,
+        reuse_timeouts,
+        store,
     } = opts;
 
+    if repetitions == 0 {
+        bail!("number of repetitions must be positive");
+    }
+    let bytes = |limit: &str, mb: Option<u64>| mb
+        .map(|mb| mb.checked_mul(1024 * 1024)
+            .with_context(|| format!("{} of {} MB is too large", limit, mb)))
+        .transpose();
+    if memory_limit == Some(0) {
+        bail!("memory limit must be positive");
+    }
+    bytes("memory limit", memory_limit)?;
+
+    let env = env.iter()
+        .map(|var| match var.split_once('=') {
+            Some((name, value)) if !name.is_empty() => Ok((name.to_owned(), value.to_owned())),
+            _ => bail!("invalid environment variable '{}' (expected NAME=VALUE)", var),
+        })
+        .collect::<Result<BTreeMap<_, _>>>()?;
+
+    let patterns = |patterns: Vec<String>| patterns.iter()
+        .map(|p| glob::Pattern::new(p).with_context(|| format!("invalid glob pattern '{}'", p)))
+        .collect::<Result<Vec<_>>>();
+    let filter = BenchmarkFilter {
+        recursive,
+        include: patterns(include)?,
+        exclude: patterns(exclude)?,
+        extensions: extensions.into_iter()
+            .map(|e| e.trim_start_matches('.').to_owned())
+            .collect(),
+    };
+
+    let job = JobConfig::<P> {
+        solvers: FromDir::from_dir(&solver_dir)?,
+        benchmarks: FromBenchDir::from_bench_dir(&bench_dir, &filter)?,
+        timeout: Duration::from_secs(timeout),
+        memory_limit,
+        repetitions,
+    };
+    dao::check_ids("solver", job.solvers.iter().map(|s| s.id()))?;
+    dao::check_ids("benchmark", job.benchmarks.iter().map(|b| b.id()))?;
+
     Ok(ApplicationConfig {
-        service: ServiceConfig { threads, },
-        dao: DaoConfig { outdir, },
-        job: JobConfig {
-            solvers: FromDir::from_dir(&solver_dir)?,
-            benchmarks: FromDir::from_dir(&bench_dir)?,
-            timeout: Duration::from_secs(timeout),
+        service: ServiceConfig {
+            threads,
+            grace_period: Duration::from_millis(grace_period),
+            on_changed_inputs,
+            reuse_timeouts,
+            only_post_process,
+            output_limits: OutputLimits {
+                stdout: bytes("stdout limit", stdout_limit)?,
+                stderr: bytes("stderr limit", stderr_limit)?,
+                files: bytes("files limit", files_limit)?,
+                on_exceeded: on_output_limit,
+            },
+            decompress,
+            stdin,
+            env: EnvConfig { clean: clean_env, keep: keep_env, set: env, redact: patterns(redact_env)? },
         },
+        dao: DaoConfig { outdir, backend: store },
+        job,
     })
 }
 
@@ -234,8 +558,8 @@ pub enum Error { #[error("{0}")]
 pub fn run_with_opts<P>(post: P, opts: Opts) -> std::result::Result<P::Reduced, Error>
 where
     P: Benchmarker + Sync,
-    P::Solver: FromDir,
-    P::Benchmark: FromDir,
+    Vec<Arc<P::Solver>>: FromDir,
+    Vec<Arc<P::Benchmark>>: FromBenchDir,
 {
     let conf = validate_opts::<P>(opts)?;
     run_with_conf(post, conf)
@@ -269,16 +593,20 @@ where
         service,
     } = conf;
 
+    let outdir = dao.outdir.clone();
     let dao = dao::create(dao)?;
     let service = service::create(service)?;
-    service.run(job, &dao, &post)
+    let (reduced, issues) = service.run(job, &dao, &post)?;
+    let report = report::store(&outdir, &reduced, &issues)?;
+    tprintln!("report written to {}", report.display());
+    Ok(reduced)
 }
 
 pub fn main_with_opts<P>(post: P, opts: Opts) -> std::result::Result<(), Error>
 where
     P: Benchmarker + Sync,
-    P::Solver: FromDir,
-    P::Benchmark: FromDir,
+    Vec<Arc<P::Solver>>: FromDir,
+    Vec<Arc<P::Benchmark>>: FromBenchDir,
 {
     match run_with_opts(post, opts) {
         Ok(_) | Err(Error::TermSignal(TermSignal)) => Ok(()),
@@ -288,6 +616,157 @@ where
 
 
 
+/// Postprocesses the results that are already stored, without running anything.
+pub fn report_with_opts<P>(post: P, opts: Opts) -> std::result::Result<P::Reduced, Error>
+where
+    P: Benchmarker + Sync,
+    Vec<Arc<P::Solver>>: FromDir,
+    Vec<Arc<P::Benchmark>>: FromBenchDir,
+{
+    run_with_opts(post, Opts { only_post_process: true, ..opts })
+}
+
+/// Prints the summary of the report stored in the directory `report` again, and returns its
+/// reduced result.
+pub fn render_report<P>(report: &Path) -> std::result::Result<P::Reduced, Error>
+where
+    P: Benchmarker,
+{
+    Ok(report::render(report)?)
+}
+
+/// Prints and returns how many runs of the job are done, failed or missing.
+pub fn status_with_opts<P>(opts: Opts) -> std::result::Result<Status, Error>
+where
+    P: Benchmarker + Sync,
+    Vec<Arc<P::Solver>>: FromDir,
+    Vec<Arc<P::Benchmark>>: FromBenchDir,
+{
+    let ApplicationConfig { job, dao, service } = validate_opts::<P>(opts)?;
+    let dao = dao::create(dao)?;
+    let service = service::create(service)?;
+    service.status(job, &dao)
+}
+
+/// Deletes failed results, and results of solvers or benchmarks that are not part of the job.
+pub fn clean_with_opts<P>(opts: Opts) -> std::result::Result<(), Error>
+where
+    P: Benchmarker + Sync,
+    Vec<Arc<P::Solver>>: FromDir,
+    Vec<Arc<P::Benchmark>>: FromBenchDir,
+{
+    let ApplicationConfig { job, dao, service } = validate_opts::<P>(opts)?;
+    let dao = dao::create(dao)?;
+    let service = service::create(service)?;
+    service.clean(job, &dao)
+}
+
+/// Moves results stored by earlier versions to their current location, and returns how many
+/// results have been moved.
+pub fn migrate_with_opts<P>(opts: Opts) -> std::result::Result<usize, Error>
+where
+    P: Benchmarker + Sync,
+    Vec<Arc<P::Solver>>: FromDir,
+    Vec<Arc<P::Benchmark>>: FromBenchDir,
+{
+    let ApplicationConfig { job, dao, service } = validate_opts::<P>(opts)?;
+    let dao = dao::create(dao)?;
+    let service = service::create(service)?;
+    service.migrate(job, &dao)
+}
+
+/// Imports the results of the directory based result tree `opts.from` into the result store, and
+/// returns how many results have been imported.
+pub fn import_with_opts<P>(opts: ImportOpts) -> std::result::Result<usize, Error>
+where
+    P: Benchmarker,
+{
+    let ImportOpts { outdir, store, from } = opts;
+    let dao = dao::create::<P>(DaoConfig { outdir, backend: store })?;
+    Ok(dao::import_dir::<P, _>(&from, &dao)?)
+}
+
+/// Prints the stored results of a single solver on a single benchmark.
+pub fn show_with_opts<P>(opts: ShowOpts) -> std::result::Result<(), Error>
+where
+    P: Benchmarker + Sync,
+    Vec<Arc<P::Solver>>: FromDir,
+    Vec<Arc<P::Benchmark>>: FromBenchDir,
+{
+    let ShowOpts { opts, solver, benchmark } = opts;
+    let ApplicationConfig { job, dao, .. } = validate_opts::<P>(opts)?;
+    let runs = job.runs()
+        .into_iter()
+        .filter(|r| id_matches(r.solver().id(), &solver) && id_matches(r.benchmark().id(), &benchmark))
+        .collect::<Vec<_>>();
+    if runs.is_empty() {
+        return Err(anyhow!("there is no solver {} or no benchmark {}", solver, benchmark).into());
+    }
+    let dao = dao::create(dao)?;
+    for run in runs {
+        match dao.read_result(&run)? {
+            Some(res) => write_result(&res, io::stdout().lock())?,
+            None => match dao.failure(&run)? {
+                Some(reason) => println!("{}: failed\n{}", run, reason),
+                None => println!("{}: missing", run),
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Whether `query` is the id of a solver or benchmark, or the file name of its id.
+fn id_matches(id: &impl std::fmt::Display, query: &str) -> bool {
+    let id = id.to_string();
+    id == query || Path::new(&id).file_name() == Some(query.as_ref())
+}
+
+fn write_result<P, W>(res: &BenchRunResult<P>, mut out: W) -> Result<()>
+where
+    P: Benchmarker,
+    W: io::Write,
+{
+    writeln!(out, "{}", res.run)?;
+    writeln!(out, "command:     {}", res.display_command())?;
+    writeln!(out, "status:      {:?}", res.status)?;
+    match res.exit_status {
+        Some(code) => writeln!(out, "exit status: {}", code)?,
+        None => writeln!(out, "exit status: killed")?,
+    }
+    writeln!(out, "time:        {:.3}s", res.time.as_secs_f64())?;
+    if !res.truncated.is_empty() {
+        writeln!(out, "truncated:   {:?}", res.truncated)?;
+    }
+    writeln!(out, "--- stdout ---")?;
+    io::copy(&mut res.stdout()?, &mut out)?;
+    writeln!(out, "--- stderr ---")?;
+    io::copy(&mut res.stderr()?, &mut out)?;
+    Ok(())
+}
+
+/// Runs the subcommand `cli`.
+pub fn main_with_cli<P>(post: P, cli: Cli) -> std::result::Result<(), Error>
+where
+    P: Benchmarker + Sync,
+    Vec<Arc<P::Solver>>: FromDir,
+    Vec<Arc<P::Benchmark>>: FromBenchDir,
+{
+    let res = match cli {
+        Cli::Run(opts) => run_with_opts(post, opts).map(|_| ()),
+        Cli::Status(opts) => status_with_opts::<P>(opts).map(|_| ()),
+        Cli::Report(opts) => report_with_opts(post, opts).map(|_| ()),
+        Cli::Clean(opts) => clean_with_opts::<P>(opts),
+        Cli::Import(opts) => import_with_opts::<P>(opts).map(|_| ()),
+        Cli::Migrate(opts) => migrate_with_opts::<P>(opts).map(|_| ()),
+        Cli::Show(opts) => show_with_opts::<P>(opts),
+        Cli::Render { report } => render_report::<P>(&report).map(|_| ()),
+    };
+    match res {
+        Ok(()) | Err(Error::TermSignal(TermSignal)) => Ok(()),
+        Err(Error::Anyhow(e)) => Err(e.into()),
+    }
+}
+
 pub fn main_with_conf<P>(post: P, conf: ApplicationConfig<P>) -> std::result::Result<(), Error>
 where
     P: Benchmarker + Sync,
@@ -26,6 +26,6 @@ impl Benchmarker for NopBenchmarker {
 }
 
 fn main() -> Result<()> {
-    benchmark_runner::main_with_opts(NopBenchmarker, Opts::parse())?;
+    benchmark_runner::main_with_cli(NopBenchmarker, Cli::parse())?;
     Ok(())
 }
@@ -0,0 +1,181 @@
+use anyhow::*;
+use std::collections::*;
+use std::fs;
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Child, ExitStatus};
+use std::time::*;
+use std::result::Result::Ok;
+use crate::dto::ResourceUsage;
+
+/// The fields of `/proc/<pid>/stat` we are interested in.
+struct ProcStat {
+    pgrp: u32,
+    /// resident set size in pages
+    rss: u64,
+}
+
+fn read_stat(pid: u32) -> Option<ProcStat> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // the command name is enclosed in parenthesis and may contain whitespaces itself
+    let fields = stat[stat.rfind(')')? + 1..].split_whitespace().collect::<Vec<_>>();
+    Some(ProcStat {
+        pgrp: fields.get(2)?.parse().ok()?,
+        rss: fields.get(21)?.parse().ok()?,
+    })
+}
+
+/// The children of all threads of the process `pid`.
+fn read_children(pid: u32) -> Vec<u32> {
+    let tasks = match fs::read_dir(format!("/proc/{}/task", pid)) {
+        Ok(tasks) => tasks,
+        Err(_) => return vec![],
+    };
+    tasks.filter_map(|task| fs::read_to_string(task.ok()?.path().join("children")).ok())
+        .flat_map(|children| children.split_whitespace().filter_map(|c| c.parse().ok()).collect::<Vec<_>>())
+        .collect()
+}
+
+fn page_size() -> u64 {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as u64 }
+}
+
+/// Returns the resident set size in bytes of the process group led by `leader`, which is the
+/// memory the memory limit of a run applies to.
+///
+/// Only the descendants of `leader` are inspected, by following the `children` files of `/proc`,
+/// rather than all processes of the machine. Descendants that left the group are not counted, as
+/// they are not killed together with it either, and neither are members that have been reparented
+/// because their parent terminated. Processes that terminate while the tree is walked are
+/// silently ignored.
+pub(crate) fn group_rss(leader: u32) -> u64 {
+    let mut total = 0;
+    let mut visited = BTreeSet::new();
+    let mut todo = vec![leader];
+    while let Some(pid) = todo.pop() {
+        if !visited.insert(pid) {
+            continue;
+        }
+        match read_stat(pid) {
+            Some(stat) if stat.pgrp == leader => total += stat.rss,
+            _ => continue,
+        }
+        todo.extend(read_children(pid));
+    }
+    total * page_size()
+}
+
+fn timeval_to_duration(t: libc::timeval) -> Duration {
+    Duration::new(t.tv_sec as u64, t.tv_usec as u32 * 1000)
+}
+
+impl From<libc::rusage> for ResourceUsage {
+    fn from(r: libc::rusage) -> Self {
+        ResourceUsage {
+            user_time: timeval_to_duration(r.ru_utime),
+            system_time: timeval_to_duration(r.ru_stime),
+            // linux reports ru_maxrss in kilobytes
+            max_rss: r.ru_maxrss as u64 * 1024,
+            minor_page_faults: r.ru_minflt as u64,
+            major_page_faults: r.ru_majflt as u64,
+            voluntary_context_switches: r.ru_nvcsw as u64,
+            involuntary_context_switches: r.ru_nivcsw as u64,
+        }
+    }
+}
+
+fn wait4(child: &Child, options: libc::c_int) -> Result<Option<(ExitStatus, ResourceUsage)>> {
+    loop {
+        let mut status = 0;
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        let pid = unsafe { libc::wait4(child.id() as libc::pid_t, &mut status, options, &mut usage) };
+        match pid {
+            0 => return Ok(None),
+            -1 => {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(err).context("failed to wait for child process");
+                }
+            }
+            _ => return Ok(Some((ExitStatus::from_raw(status), usage.into()))),
+        }
+    }
+}
+
+/// Waits for `child` to terminate, at most for the duration `timeout`. If it terminates the
+/// child is reaped and its exit status is returned together with its resource usage.
+///
+/// In contrast to `Child::wait` this uses `wait4`, hence the child must not have been reaped
+/// already.
+pub(crate) fn wait_timeout(child: &Child, timeout: Duration) -> Result<Option<(ExitStatus, ResourceUsage)>> {
+    // determines how precisely we measure the wall clock time of a run
+    let resolution = Duration::from_millis(10);
+    let start = Instant::now();
+    loop {
+        if let Some(res) = wait4(child, libc::WNOHANG)? {
+            return Ok(Some(res));
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            return Ok(None);
+        }
+        std::thread::sleep(std::cmp::min(resolution, timeout - elapsed));
+    }
+}
+
+/// Blocks until `child` terminates, reaps it, and returns its exit status and resource usage.
+pub(crate) fn wait(child: &Child) -> Result<(ExitStatus, ResourceUsage)> {
+    Ok(wait4(child, 0)?.expect("blocking wait4 returned without result"))
+}
+
+/// Sends `signal` to the process group of `child`. The child is expected to be the leader of its
+/// own process group.
+fn signal_group(child: &Child, signal: libc::c_int) -> Result<()> {
+    let res = unsafe { libc::kill(-(child.id() as libc::pid_t), signal) };
+    let err = io::Error::last_os_error();
+    if res == -1 && err.raw_os_error() != Some(libc::ESRCH) {
+        return Err(err).context("failed to kill child process group");
+    }
+    Ok(())
+}
+
+/// Terminates the whole process group of `child`, and reaps the child.
+///
+/// The group is first sent `SIGTERM`. If the child did not terminate after `grace_period` the
+/// group is killed using `SIGKILL`. Processes of the group that outlive the child are killed as
+/// well.
+pub(crate) fn kill_group(child: &Child, grace_period: Duration) -> Result<(ExitStatus, ResourceUsage)> {
+    signal_group(child, libc::SIGTERM)?;
+    let res = match wait_timeout(child, grace_period)? {
+        Some(res) => res,
+        None => {
+            signal_group(child, libc::SIGKILL)?;
+            wait(child)?
+        }
+    };
+    signal_group(child, libc::SIGKILL)?;
+    Ok(res)
+}
+
+/// Kills the processes of the process group of `child` that outlived it. The child must have
+/// been reaped already.
+pub(crate) fn kill_remaining(child: &Child) -> Result<()> {
+    signal_group(child, libc::SIGKILL)
+}
+
+/// Whether a process with the given pid exists on this machine.
+pub(crate) fn is_alive(pid: u32) -> bool {
+    let res = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    res == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// The name of this machine.
+pub(crate) fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    let res = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+    if res != 0 {
+        return "localhost".to_owned();
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
@@ -0,0 +1,98 @@
+//! A report is the reduced result of a job. Each report is stored in its own timestamped
+//! directory `<outdir>/reports/<timestamp>/`, containing the reduced value as `reduced.json`, the
+//! soundness issues as `soundness.json`, and its summary as `summary.txt`.
+//! `<outdir>/reports/latest` links to the most recent report.
+use super::*;
+use std::fs;
+
+fn reduced_json(dir: &Path) -> PathBuf {
+    dir.join("reduced.json")
+}
+
+fn soundness_json(dir: &Path) -> PathBuf {
+    dir.join("soundness.json")
+}
+
+fn summary_txt(dir: &Path) -> PathBuf {
+    dir.join("summary.txt")
+}
+
+/// Writes the runs whose answer contradicts the expected one, if there are any.
+pub(crate) fn write_soundness_issues<W: io::Write>(mut out: W, issues: &[SoundnessIssue]) -> Result<()> {
+    if !issues.is_empty() {
+        writeln!(out, "soundness issues: {}", issues.len())?;
+        for issue in issues {
+            writeln!(out, "    {}", issue)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_summary<R: Summerizable>(dir: &Path, reduced: &R, issues: &[SoundnessIssue]) -> Result<()> {
+    use std::io::Write;
+    let file = summary_txt(dir);
+    let mut out = io::BufWriter::new(create_file(&file)?);
+    reduced.write_summary(&mut out)
+        .and_then(|()| write_soundness_issues(&mut out, issues))
+        .and_then(|()| Ok(out.flush()?))
+        .with_context(|| format!("failed to write summary to {}", file.display()))
+}
+
+/// Stores `reduced` and the soundness `issues` as a new report in `outdir`, and returns the
+/// report's directory.
+pub(crate) fn store<R>(outdir: &Path, reduced: &R, issues: &[SoundnessIssue]) -> Result<PathBuf>
+where
+    R: Serialize + Summerizable,
+{
+    let reports = outdir.join("reports");
+    create_dir_all(&reports)?;
+    let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+    let mut dir = reports.join(&timestamp);
+    // multiple jobs might finish within the same second
+    for i in 1.. {
+        match fs::create_dir(&dir) {
+            Ok(()) => break,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                dir = reports.join(format!("{}_{}", timestamp, i));
+            }
+            Err(e) => return Err(e).with_context(|| format!("failed to create directory {}", dir.display())),
+        }
+    }
+    write_json(create_file(reduced_json(&dir))?, reduced)?;
+    write_json(create_file(soundness_json(&dir))?, &issues)?;
+    write_summary(&dir, reduced, issues)?;
+
+    // the link is replaced atomically, so that it always points to a complete report
+    let latest = reports.join("latest");
+    let tmp = reports.join(format!(".latest.{}", std::process::id()));
+    if fs::symlink_metadata(&tmp).is_ok() {
+        fs::remove_file(&tmp)
+            .with_context(|| format!("failed to remove {}", tmp.display()))?;
+    }
+    std::os::unix::fs::symlink(dir.file_name().unwrap(), &tmp)
+        .with_context(|| format!("failed to link {}", tmp.display()))?;
+    fs::rename(&tmp, &latest)
+        .with_context(|| format!("failed to replace {}", latest.display()))?;
+    Ok(dir)
+}
+
+/// Reads the reduced value of the report stored in `dir`, and renders its summary again. The
+/// summary is printed and replaces the report's `summary.txt`.
+pub(crate) fn render<R>(dir: &Path) -> Result<R>
+where
+    R: DeserializeOwned + Summerizable,
+{
+    let reduced: R = read_json(reduced_json(dir))
+        .with_context(|| format!("failed to read report {}", dir.display()))?;
+    // reports stored before soundness issues were detected do not contain them
+    let issues: Vec<SoundnessIssue> = if soundness_json(dir).exists() {
+        read_json(soundness_json(dir))
+            .with_context(|| format!("failed to read soundness issues of report {}", dir.display()))?
+    } else {
+        vec![]
+    };
+    write_summary(dir, &reduced, &issues)?;
+    reduced.write_summary(io::stdout().lock())?;
+    write_soundness_issues(io::stdout().lock(), &issues)?;
+    Ok(reduced)
+}
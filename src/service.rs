@@ -3,18 +3,24 @@ use std::result::Result;
 use super::Error;
 use log::*;
 use atty::Stream;
+use std::os::unix::process::CommandExt;
+use crate::fingerprint::FingerprintCache;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
 
 struct ServiceImpl {
     conf: ServiceConfig,
+    fingerprints: FingerprintCache,
 }
 
 //TODO get rid of this clone
 pub(crate) fn create(conf: ServiceConfig) -> anyhow::Result<impl Service> {
-    Ok(ServiceImpl { conf })
+    Ok(ServiceImpl { conf, fingerprints: FingerprintCache::default() })
 }
 
 pub(crate) trait Service {
-    fn run<D, P>(&self, job: JobConfig<P>, dao: &D, post: &P) -> Result<P::Reduced, Error>
+    /// Returns the reduced results, and the soundness issues among them.
+    fn run<D, P>(&self, job: JobConfig<P>, dao: &D, post: &P) -> Result<(P::Reduced, Vec<SoundnessIssue>), Error>
     where
         D: Dao<P> + Sync,
         P: Benchmarker + Sync;
@@ -22,6 +28,22 @@ pub(crate) trait Service {
     fn run_single<P>(&self, ident: &BenchRunConf<P>) -> Result<BenchRunResult<P>, Error>
     where
         P: Benchmarker + Sync;
+    /// Counts the finished, failed, and missing runs of the job per solver.
+    fn status<D, P>(&self, job: JobConfig<P>, dao: &D) -> Result<Status, Error>
+    where
+        D: Dao<P> + Sync,
+        P: Benchmarker + Sync;
+    /// Deletes failed results, and results of solvers or benchmarks that are not part of the job.
+    fn clean<D, P>(&self, job: JobConfig<P>, dao: &D) -> Result<(), Error>
+    where
+        D: Dao<P> + Sync,
+        P: Benchmarker + Sync;
+    /// Moves results stored by earlier versions to the locations of the corresponding runs of
+    /// the job. Stored benchmarks are matched with the ones of the job by their file.
+    fn migrate<D, P>(&self, job: JobConfig<P>, dao: &D) -> Result<usize, Error>
+    where
+        D: Dao<P> + Sync,
+        P: Benchmarker + Sync;
 }
 
 impl Service for ServiceImpl {
@@ -31,12 +53,12 @@ impl Service for ServiceImpl {
         // <P as Benchmarker>::BAnnot: Clone,
     {
         tprintln!("Running: {}...", conf.display_command());
-        let out = run_command(&conf);
+        let out = run_command(conf, &self.conf, &self.fingerprints);
         tprintln!("Finished.");
         out
     }
 
-    fn run<D, P>(&self, job: JobConfig<P>, dao: &D, post: &P) -> Result<P::Reduced, Error>
+    fn run<D, P>(&self, job: JobConfig<P>, dao: &D, post: &P) -> Result<(P::Reduced, Vec<SoundnessIssue>), Error>
     where
         D: Dao<P> + Sync,
         P: Benchmarker + Sync,
@@ -54,23 +76,42 @@ impl Service for ServiceImpl {
             "failed to set number of threads"
         );
 
-        let (mut done, todo): (Vec<_>, Vec<_>) = {
-            let bs = &job.benchmarks[..];
-            let cs = &job.solvers[..];
-            let ui = Ui::new("Reading old results", bs.len() * cs.len());
-            // let config = &config;
-            bs.par_iter()
-                .flat_map(move |benchmark| {
-                    cs.par_iter().map(move |solver| BenchRunConf {
-                        timeout: job.timeout,
-                        benchmark: benchmark.clone(),
-                        solver: solver.clone(),
-                    })
-                })
+        // outdated results are reported rather than ignored, if they cannot be rerun anyway
+        let on_changed_inputs = match self.conf.on_changed_inputs {
+            ChangedInputs::Rerun if self.conf.only_post_process => ChangedInputs::Warn,
+            policy => policy,
+        };
+
+        let (done, todo): (Vec<_>, Vec<_>) = {
+            let runs = job.runs();
+            let ui = Ui::new("Reading old results", runs.len());
+            runs.into_par_iter()
                 .filter(|_| !shall_terminate())
                 .partition_map(|c| {
-                    let result = match dao.read_result(&c) {
-                        Ok(Some(res)) => Either::Left(res),
+                    let timeout = c.timeout;
+                    let result = match self.lookup_result(dao, &c) {
+                        Ok(Some(res)) => match changed_inputs(&self.fingerprints, &self.conf.env, &c, &res) {
+                            Ok(changed) if changed.is_empty() => Either::Left(res),
+                            Ok(changed) => {
+                                let changed = changed.join(", ");
+                                match on_changed_inputs {
+                                    ChangedInputs::Keep => Either::Left(res),
+                                    ChangedInputs::Warn => {
+                                        ui.println(format_args!("warning: {} changed since {} has been run", changed, c));
+                                        Either::Left(res)
+                                    }
+                                    ChangedInputs::Rerun => {
+                                        // the outdated result is replaced once the run has been claimed
+                                        info!("rerunning {} because {} changed", c, changed);
+                                        Either::Right(c)
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                ui.println(format_args!("failed to check whether inputs of {} changed: {:#}", c, e));
+                                Either::Left(res)
+                            }
+                        },
                         Ok(None) => Either::Right(c),
                         Err(e) => {
                             ui.println(format_args!("failed to read result: {:#}", e));
@@ -78,63 +119,293 @@ impl Service for ServiceImpl {
                         }
                     };
                     ui.progress();
-                    result
+                    // results that have been obtained with another timeout are reported for the
+                    // requested one
+                    result.map_left(|mut res| {
+                        res.requested_timeout = (res.run.timeout != timeout).then_some(timeout);
+                        res
+                    })
                 })
         };
 
-        let remove_files = |ui: &Ui, conf: &BenchRunConf<P>, reason: FormatArgs| {
-            eprintln!("error: {}", reason);
-            match dao.remove_result(&conf, reason)
-            {
-                Ok(()) => ui.println(format_args!("removed output files for {} {}", conf.solver().id(), conf.benchmark().id())),
-                Err(e) => ui.println(format_args!("failed to remove output files: {:#}", e)),
+        if self.conf.only_post_process {
+            report_missing(&todo);
+            // results are only read, hence they are kept even if postprocessing fails
+            return self.postprocess(job, done, vec![], dao, post, |ui: &Ui, conf: &BenchRunConf<P>, reason: FormatArgs| {
+                ui.println(format_args!("error: {}: {}", conf, reason))
+            });
+        }
+
+        let remove_files = |ui: &Ui, conf: &BenchRunConf<P>, reason: FormatArgs| remove_files(dao, ui, conf, reason);
+
+        // results are mapped right away, so that failures show up while the job is still running
+        let map = |ui: &Ui, res: BenchRunResult<P>| match self.map_result(dao, post, &res) {
+            Ok(mapped) => Some((res, mapped)),
+            Err(e) => {
+                remove_files(ui, &res.run, format_args!("{:#}", e));
+                None
+            }
+        };
+
+        let run = |ui: &Ui, conf: &BenchRunConf<P>| {
+            // another runner instance sharing the store might have finished this run in the meantime
+            match self.reusable_result(dao, conf) {
+                Ok(Some(res)) => return map(ui, res),
+                Ok(None) => {}
+                Err(e) => info!("failed to read result: {:#}", e),
+            }
+            match run_command(conf, &self.conf, &self.fingerprints) {
+                Ok(x) => match dao.store_result(&x) {
+                    // the stored result reads its outputs from the store, not from the temporary directory
+                    Ok(stored) => map(ui, stored),
+                    Err(e) => {
+                        eprintln!("failed to store result: {:#}", e);
+                        map(ui, x)
+                    }
+                },
+                Err(Error::TermSignal(TermSignal)) => None,
+                Err(e) => {
+                    remove_files(ui, conf, format_args!("failed to run {}: {:#}", conf.display_command(), e));
+                    None
+                }
             }
         };
 
+        let mut finished = vec![];
         {
             let ui = Ui::new("Benchmarking", todo.len());
-            done.par_extend(todo[..].into_par_iter().filter_map(|conf| {
-                if shall_terminate() {
-                    None
-                } else {
-                    let result = match run_command(&conf) {
-                        Ok(x) => {
-                            if let Err(e) = dao.store_result(&x) {
-                                eprintln!("failed to store result: {:#}", e);
-                            }
-                            Some(x)
+            let mut todo = todo;
+            let mut waiting = 0;
+            loop {
+                let (results, claimed): (Vec<_>, Vec<_>) = todo.into_par_iter()
+                    .filter(|_| !shall_terminate())
+                    .partition_map(|conf| match dao.claim(&conf) {
+                        Ok(Some(_claim)) => {
+                            let result = run(&ui, &conf);
+                            ui.progress();
+                            Either::Left(result)
                         }
-                        Err(Error::TermSignal(TermSignal)) => None,
+                        Ok(None) => Either::Right(conf),
                         Err(e) => {
-                            remove_files(&ui, &conf, format_args!("failed to run {}: {:#}", conf.display_command(), e));
-                            None
+                            ui.println(format_args!("failed to claim {}: {:#}", conf, e));
+                            ui.progress();
+                            Either::Left(None)
                         }
-                    };
-                    ui.progress();
-                    result
+                    });
+                finished.extend(results.into_iter().flatten());
+                if claimed.is_empty() || shall_terminate() {
+                    break;
                 }
-            }));
+                // the remaining runs are claimed by other runner instances. we wait for their
+                // results, or take over if they give up on a run.
+                if claimed.len() != waiting {
+                    waiting = claimed.len();
+                    ui.println(format_args!("waiting for {} runs claimed by other runner instances", waiting));
+                }
+                std::thread::sleep(Duration::from_secs(5));
+                todo = claimed;
+            }
         }
 
         if shall_terminate() {
             return Err(Error::TermSignal(TermSignal));
         }
 
-        let mapped: Vec<_> = {
-            // TODO postprocess directly after benchmarking to get online warnings when runs are failing
+        self.postprocess(job, done, finished, dao, post, remove_files)
+    }
+
+    fn status<D, P>(&self, job: JobConfig<P>, dao: &D) -> Result<Status, Error>
+    where
+        D: Dao<P> + Sync,
+        P: Benchmarker + Sync,
+    {
+        let runs = job.runs();
+        let ui = Ui::new("Reading results", runs.len());
+        let counts = runs.into_par_iter()
+            .map(|c| {
+                let state = match self.lookup_result(dao, &c) {
+                    Ok(Some(_)) => RunState::Done,
+                    Ok(None) => match dao.failure(&c) {
+                        Ok(Some(_)) => RunState::Failed,
+                        Ok(None) => RunState::Missing,
+                        Err(e) => {
+                            ui.println(format_args!("failed to read failure of {}: {:#}", c, e));
+                            RunState::Missing
+                        }
+                    },
+                    Err(e) => {
+                        ui.println(format_args!("failed to read result: {:#}", e));
+                        RunState::Failed
+                    }
+                };
+                ui.progress();
+                (c.solver().id().to_string(), state)
+            })
+            .collect::<Vec<_>>();
+        drop(ui);
+
+        let mut status = Status::new();
+        for (solver, state) in counts {
+            let s = status.entry(solver).or_default();
+            match state {
+                RunState::Done => s.done += 1,
+                RunState::Failed => s.failed += 1,
+                RunState::Missing => s.missing += 1,
+            }
+        }
+        write_status(&status, std::io::stdout().lock())?;
+        Ok(status)
+    }
+
+    fn clean<D, P>(&self, job: JobConfig<P>, dao: &D) -> Result<(), Error>
+    where
+        D: Dao<P> + Sync,
+        P: Benchmarker + Sync,
+    {
+        // results of runs that are claimed by a runner instance are kept, as it might be
+        // storing or removing them right now
+        let failures = dao.delete_failures()?;
+        println!("deleted {} failed results", failures);
+        let incomplete = dao.delete_incomplete()?;
+        println!("deleted {} incompletely stored results", incomplete);
+
+        let solvers = job.solvers.iter().map(|s| s.id().to_string()).collect::<BTreeSet<_>>();
+        let benchmarks = job.benchmarks.iter().map(|b| b.id().to_string()).collect::<BTreeSet<_>>();
+        let orphans = dao.stored_runs()?
+            .into_iter()
+            .filter(|r| !solvers.contains(&r.solver().id().to_string())
+                     || !benchmarks.contains(&r.benchmark().id().to_string()))
+            .collect::<Vec<_>>();
+        let mut deleted = 0;
+        for run in &orphans {
+            match dao.claim(run)? {
+                Some(_claim) => {
+                    info!("deleting orphan result {}", run);
+                    dao.delete_result(run)?;
+                    deleted += 1;
+                }
+                None => info!("keeping orphan result {}, as it is claimed", run),
+            }
+        }
+        println!("deleted {} results of solvers or benchmarks that are no longer present", deleted);
+        Ok(())
+    }
+
+    fn migrate<D, P>(&self, job: JobConfig<P>, dao: &D) -> Result<usize, Error>
+    where
+        D: Dao<P> + Sync,
+        P: Benchmarker + Sync,
+    {
+        let benchmarks = job.benchmarks.iter()
+            .filter_map(|b| Some((b.file()?.to_owned(), b.clone())))
+            .collect::<BTreeMap<_, _>>();
+        let moved = dao.migrate(|run| {
+            let benchmark = benchmarks.get(run.benchmark().file()?)?;
+            if benchmark.as_ref() == run.benchmark() {
+                return None;
+            }
+            Some(BenchRunConf { benchmark: benchmark.clone(), ..run.clone() })
+        })?;
+        println!("moved {} results", moved);
+        Ok(moved)
+    }
+}
+
+enum RunState {
+    Done,
+    Failed,
+    Missing,
+}
+
+/// Prints how many runs are missing per solver. The missing runs themselves are logged.
+fn report_missing<P: Benchmarker>(missing: &[BenchRunConf<P>]) {
+    let mut counts = BTreeMap::new();
+    for run in missing {
+        info!("missing result {}", run);
+        *counts.entry(run.solver().id().to_string()).or_insert(0) += 1;
+    }
+    for (solver, count) in counts {
+        println!("{}: {} results missing", solver, count);
+    }
+}
+
+fn write_status<W: std::io::Write>(status: &Status, mut out: W) -> anyhow::Result<()> {
+    let width = status.keys().map(|s| s.len()).chain(Some("solver".len())).max().unwrap_or(0);
+    writeln!(out, "{:<width$}  {:>8}  {:>8}  {:>8}", "solver", "done", "failed", "missing", width = width)?;
+    for (solver, s) in status {
+        writeln!(out, "{:<width$}  {:>8}  {:>8}  {:>8}", solver, s.done, s.failed, s.missing, width = width)?;
+    }
+    Ok(())
+}
+
+fn remove_files<D, P>(dao: &D, ui: &Ui, conf: &BenchRunConf<P>, reason: FormatArgs)
+where
+    D: Dao<P>,
+    P: Benchmarker,
+{
+    eprintln!("error: {}", reason);
+    match dao.remove_result(conf, reason)
+    {
+        Ok(()) => ui.println(format_args!("removed output files for {} {}", conf.solver().id(), conf.benchmark().id())),
+        Err(e) => ui.println(format_args!("failed to remove output files: {:#}", e)),
+    }
+}
+
+/// The runs whose answer contradicts the expected result of their benchmark. The expected result
+/// is taken from the benchmarks of `job`, as the metadata of a benchmark might have been updated
+/// since its results have been stored.
+fn soundness_issues<P: Benchmarker>(post: &P, job: &JobConfig<P>, mapped: &[(BenchRunResult<P>, P::Mapped)]) -> Vec<SoundnessIssue> {
+    let benchmarks = job.benchmarks.iter()
+        .map(|b| (b.id().to_string(), &**b))
+        .collect::<BTreeMap<_, _>>();
+    mapped.iter()
+        .filter_map(|(res, m)| {
+            let benchmark = benchmarks.get(&res.benchmark().id().to_string())
+                .copied()
+                .unwrap_or_else(|| res.benchmark());
+            let expected = benchmark.expected()?;
+            let answer = post.answer(res, m)?;
+            post.contradicts(expected, &answer).then(|| SoundnessIssue {
+                solver: res.solver().id().to_string(),
+                benchmark: res.benchmark().id().to_string(),
+                repetition: res.repetition(),
+                expected: expected.to_owned(),
+                answer,
+            })
+        })
+        .sorted()
+        .collect()
+}
+
+impl ServiceImpl {
+    /// Maps the results `done`, reduces them together with the results that have been `mapped`
+    /// already, and prints the summary, followed by the soundness issues. Results that cannot be
+    /// mapped are skipped after calling `on_error`.
+    fn postprocess<D, P, F>(
+        &self,
+        job: &JobConfig<P>,
+        done: Vec<BenchRunResult<P>>,
+        mapped: Vec<(BenchRunResult<P>, P::Mapped)>,
+        dao: &D,
+        post: &P,
+        on_error: F,
+    ) -> Result<(P::Reduced, Vec<SoundnessIssue>), Error>
+    where
+        D: Dao<P> + Sync,
+        P: Benchmarker + Sync,
+        F: Fn(&Ui, &BenchRunConf<P>, FormatArgs) + Sync,
+    {
+        let mut mapped = mapped;
+        {
             let ui = Ui::new("Postprocessing", done.len());
 
-            done.par_iter()
+            let new = done.par_iter()
                 .filter_map(|x| {
                     Some({
-                        let res = match post.map(&x) {
+                        let res = match self.map_result(dao, post, x) {
                             Ok(mapped) => (x.clone(), mapped),
                             Err(e) => {
-                                remove_files(
-                                    &ui,
-                                    &x.run,
-                                    format_args!("failed to prostprocess: {:#}", e),
-                                );
+                                on_error(&ui, &x.run, format_args!("{:#}", e));
                                 return None;
                             }
                         };
@@ -146,11 +417,14 @@ impl Service for ServiceImpl {
                         }
                     })
                 })
-                .collect::<std::result::Result<Vec<_>, _>>()?
-        };
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            mapped.extend(new);
+        }
+
+        let issues = soundness_issues(post, job, &mapped);
 
         //TODO store this via dto
-        let reduced = post.reduce(&job, mapped)?;
+        let reduced = post.reduce(job, mapped)?;
 
         if shall_terminate() {
             return Err(Error::TermSignal(TermSignal));
@@ -161,82 +435,455 @@ impl Service for ServiceImpl {
         //     .with_context(|| format!("failed to create directory: {}", dir.display()))?;
         // println!("writing to output dir: {}", dir.display());
 
-        // TODO serialize summary to file
         reduced.write_summary(std::io::stdout().lock())?;
-        Ok(reduced)
+        report::write_soundness_issues(std::io::stdout().lock(), &issues)?;
+        Ok((reduced, issues))
+    }
+
+    /// Maps `res`. If the benchmarker enables caching, a mapped value cached in `dao` is used if
+    /// possible, and newly mapped values are cached.
+    fn map_result<D, P>(&self, dao: &D, post: &P, res: &BenchRunResult<P>) -> anyhow::Result<P::Mapped>
+    where
+        D: Dao<P>,
+        P: Benchmarker,
+    {
+        self.map_cached(dao, post, res).context("failed to postprocess")
+    }
+
+    fn map_cached<D, P>(&self, dao: &D, post: &P, res: &BenchRunResult<P>) -> anyhow::Result<P::Mapped>
+    where
+        D: Dao<P>,
+        P: Benchmarker,
+    {
+        let cache = match post.map_cache() {
+            Some(cache) => cache,
+            None => return post.map(res),
+        };
+        // the mapped value might depend on the benchmark's metadata, which is updated by `migrate`
+        let benchmark = serde_json::to_value(res.benchmark()).context("failed to serialize benchmark")?;
+        match dao.read_mapped(&res.run) {
+            Ok(Some(entry)) if entry.version == cache.version && entry.benchmark == benchmark => match (cache.deserialize)(entry.value) {
+                Ok(mapped) => return Ok(mapped),
+                Err(e) => info!("failed to deserialize cached mapped value of {}: {}", res.run, e),
+            },
+            Ok(_) => {}
+            Err(e) => info!("failed to read cached mapped value: {:#}", e),
+        }
+        let mapped = post.map(res)?;
+        let entry = MappedEntry {
+            version: cache.version,
+            benchmark,
+            value: (cache.serialize)(&mapped).context("failed to serialize mapped value")?,
+        };
+        if let Err(e) = dao.store_mapped(&res.run, &entry) {
+            warn!("failed to cache mapped value of {}: {:#}", res.run, e);
+        }
+        Ok(mapped)
+    }
+
+    /// Reads the stored result for `run`, unless it must be rerun because its inputs changed.
+    fn reusable_result<D, P>(&self, dao: &D, run: &BenchRunConf<P>) -> anyhow::Result<Option<BenchRunResult<P>>>
+    where
+        D: Dao<P>,
+        P: Benchmarker,
+    {
+        Ok(match self.lookup_result(dao, run)? {
+            Some(mut res) if self.conf.on_changed_inputs != ChangedInputs::Rerun
+                || changed_inputs(&self.fingerprints, &self.conf.env, run, &res)?.is_empty() => {
+                res.requested_timeout = (res.run.timeout != run.timeout).then_some(run.timeout);
+                Some(res)
+            }
+            _ => None,
+        })
+    }
 
+    /// Reads the stored result for `run`. If there is none and reusing results of other timeouts
+    /// is enabled, a successful result obtained with a smaller timeout, or a timed out result
+    /// obtained with a larger timeout is returned instead. Note that the returned result refers
+    /// to the timeout it has actually been obtained with.
+    fn lookup_result<D, P>(&self, dao: &D, run: &BenchRunConf<P>) -> anyhow::Result<Option<BenchRunResult<P>>>
+    where
+        D: Dao<P>,
+        P: Benchmarker,
+    {
+        if let Some(res) = dao.read_result(run)? {
+            return Ok(Some(res));
+        }
+        if !self.conf.reuse_timeouts {
+            return Ok(None);
+        }
+        let mut timeouts = dao.timeouts(run)?;
+        // prefer results whose timeout is closest to the requested one
+        timeouts.sort_by_key(|t| t.abs_diff(run.timeout));
+        for timeout in timeouts {
+            let reusable = |status| match status {
+                BenchmarkStatus::Success => timeout < run.timeout,
+                BenchmarkStatus::Timeout => timeout > run.timeout,
+                _ => false,
+            };
+            if timeout == run.timeout {
+                continue;
+            }
+            let other = BenchRunConf { timeout, ..run.clone() };
+            match dao.read_result(&other)? {
+                Some(res) if reusable(res.status) => {
+                    info!("reusing result with timeout {}s for {}", timeout.as_secs(), run);
+                    return Ok(Some(res));
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
     }
 }
 
+/// Returns a description of the input files of `run` that have changed since `res` has been
+/// computed, and of the memory limit if `res` has been computed with another one.
+fn changed_inputs<P>(fingerprints: &FingerprintCache, env: &EnvConfig, run: &BenchRunConf<P>, res: &BenchRunResult<P>) -> anyhow::Result<Vec<String>>
+where
+    P: Benchmarker,
+{
+    let mut changed = vec![];
+    if res.run.memory_limit != run.memory_limit {
+        changed.push(match res.run.memory_limit {
+            Some(limit) => format!("memory limit (was {} MB)", limit),
+            None => "memory limit (was unlimited)".to_owned(),
+        });
+    }
+    if let (Some(old), Some(file)) = (&res.inputs.solver, run.solver().executable()) {
+        if !fingerprints.matches(old, &file)? {
+            changed.push(format!("solver {}", file.display()));
+        }
+    }
+    if let (Some(old), Some(file)) = (&res.inputs.benchmark, run.benchmark().file()) {
+        if !fingerprints.matches(old, file)? {
+            changed.push(format!("benchmark {}", file.display()));
+        }
+    }
+    if let Some(old) = &res.env {
+        // the working directory only affects the arguments of the command
+        let cmd = run.to_command(Path::new("."));
+        let new = recorded_env(env, &effective_env(&cmd, env));
+        // inherited variables are only compared in a clean environment, where they are kept
+        // explicitly, as the runner's environment changes between sessions
+        let controlled = |k: &str| env.clean
+            || env.set.contains_key(k)
+            || cmd.get_envs().any(|(var, _)| var == k);
+        let vars = old.keys().chain(new.keys())
+            .filter(|k| controlled(k) && old.get(*k) != new.get(*k))
+            .collect::<BTreeSet<_>>();
+        if !vars.is_empty() {
+            changed.push(format!("environment variables {}", vars.iter().join(", ")));
+        }
+    }
+    Ok(changed)
+}
 
-fn run_command<P>(run: &BenchRunConf<P>) -> Result<BenchRunResult<P>, Error>
+/// The environment `cmd` is run with. It consists of the runner's environment (only the kept
+/// variables in a clean environment), the variables set for all solvers, and the ones set by
+/// `cmd` itself, where later ones take precedence.
+fn effective_env(cmd: &std::process::Command, conf: &EnvConfig) -> BTreeMap<OsString, OsString> {
+    let mut env: BTreeMap<OsString, OsString> = std::env::vars_os()
+        .filter(|(k, _)| !conf.clean || conf.keep.iter().any(|keep| k == keep.as_str()))
+        .collect();
+    env.extend(conf.set.iter().map(|(k, v)| (k.into(), v.into())));
+    for (k, v) in cmd.get_envs() {
+        match v {
+            Some(v) => env.insert(k.to_owned(), v.to_owned()),
+            None => env.remove(k),
+        };
+    }
+    env
+}
+
+/// The value recorded for redacted environment variables.
+const REDACTED: &str = "<redacted>";
+
+/// The environment `env` as it is recorded with a result, where the values of the variables
+/// matching a redaction pattern are replaced.
+fn recorded_env(conf: &EnvConfig, env: &BTreeMap<OsString, OsString>) -> BTreeMap<String, String> {
+    env.iter()
+        .map(|(k, v)| {
+            let k = k.to_string_lossy().into_owned();
+            let v = if conf.redact.iter().any(|p| p.matches(&k)) {
+                REDACTED.to_owned()
+            } else {
+                v.to_string_lossy().into_owned()
+            };
+            (k, v)
+        })
+        .collect()
+}
+
+/// If decompression is enabled and the benchmark of `run` is compressed, it is decompressed into
+/// the subdirectory `benchmark` of `dir`, and the benchmark reading the decompressed copy is
+/// returned.
+fn decompressed_benchmark<P: Benchmarker>(run: &BenchRunConf<P>, conf: &ServiceConfig, dir: &Path) -> anyhow::Result<Option<P::Benchmark>> {
+    let file = match run.benchmark().file() {
+        Some(file) if conf.decompress => file,
+        _ => return Ok(None),
+    };
+    let compression = match crate::compression::Compression::of(file) {
+        Some(compression) => compression,
+        None => return Ok(None),
+    };
+    // the decompressed file must not clash with the outputs of the run, which are stored in `dir`
+    let dir = dir.join("benchmark");
+    std::fs::create_dir(&dir).context("failed to create directory for the decompressed benchmark")?;
+    let decompressed = compression.decompress(file, &dir)?;
+    match run.benchmark().with_file(&decompressed) {
+        Some(benchmark) => Ok(Some(benchmark)),
+        None => bail!("benchmark {} cannot be passed to the solver decompressed", run.benchmark().id()),
+    }
+}
+
+fn run_command<P>(run: &BenchRunConf<P>, conf: &ServiceConfig, fingerprints: &FingerprintCache) -> Result<BenchRunResult<P>, Error>
 where
     P: Benchmarker,
 {
     info!("running: {}", run.display_command());
 
+    let inputs = InputFingerprints {
+        solver: run.solver().executable().map(|f| fingerprints.get(&f)).transpose()?,
+        benchmark: run.benchmark().file().map(|f| fingerprints.get(f)).transpose()?,
+    };
+
     // let mut cmd = Command::new(run.command());
     // cmd.args(run.args());
-    let temp_dir = tempfile::tempdir().context("failed to create temp dir")?;
-    let temp_dir = temp_dir.path();
+    let tmp = KeepAlive(Some(Arc::new(tempfile::tempdir().context("failed to create temp dir")?)));
+    let temp_dir = tmp.0.as_ref().unwrap().path();
     let stdout = temp_dir.join("stdout.txt");
     let stderr = temp_dir.join("stderr.txt");
 
     let pwd = temp_dir.join("pwd");
     std::fs::create_dir(&pwd).context("failed to create runtime dir")?;
-    let mut cmd = run.to_command();
-    cmd.stdout(crate::dao::create_file(&stdout)?);
-    cmd.stderr(crate::dao::create_file(&stderr)?);
-    cmd.current_dir(&pwd);
+    let limits = &conf.output_limits;
+    let decompressed = decompressed_benchmark(run, conf, temp_dir)?;
+    let benchmark = decompressed.as_ref().unwrap_or_else(|| run.benchmark());
+    let mut cmd = match &decompressed {
+        Some(benchmark) => BenchRunConf { benchmark: Arc::new(benchmark.clone()), ..run.clone() }.to_command(&pwd),
+        None => run.to_command(&pwd),
+    };
+    // solvers must not read from the runner's stdin
+    cmd.stdin(match run.solver().stdin().unwrap_or(conf.stdin) {
+        StdinMode::Null => std::process::Stdio::null(),
+        StdinMode::Benchmark => match benchmark.file() {
+            Some(file) => crate::dao::open_file(file)?.into(),
+            None => return Err(anyhow!("solver {} reads the benchmark from stdin, but benchmark {} has no file", run.solver().id(), run.benchmark().id()).into()),
+        },
+    });
+    let env = effective_env(&cmd, &conf.env);
+    let recorded = recorded_env(&conf.env, &env);
+    cmd.env_clear().envs(&env);
+    // streams with a size limit are piped through a capture thread, the others go to their file directly
+    cmd.stdout(match limits.stdout {
+        Some(_) => std::process::Stdio::piped(),
+        None => crate::dao::create_file(&stdout)?.into(),
+    });
+    cmd.stderr(match limits.stderr {
+        Some(_) => std::process::Stdio::piped(),
+        None => crate::dao::create_file(&stderr)?.into(),
+    });
+    if cmd.get_current_dir().is_none() {
+        cmd.current_dir(&pwd);
+    }
+    // run the solver in its own process group, so that we can kill all processes it spawns
+    cmd.process_group(0);
+    let memory_limit = run.memory_limit.map(|mb| mb.saturating_mul(1024 * 1024));
     let mut child = cmd.spawn().context("failed to launch child process")?;
+    let captures = vec![
+        limits.stdout.map(|limit| Capture::spawn(child.stdout.take().unwrap(), &stdout, limit, CapturedOutput::Stdout)).transpose()?,
+        limits.stderr.map(|limit| Capture::spawn(child.stderr.take().unwrap(), &stderr, limit, CapturedOutput::Stderr)).transpose()?,
+    ].into_iter().flatten().collect::<Vec<_>>();
 
 
     use std::time::*;
 
     let start = Instant::now();
     // TODO make poll timeout relative to timeout of benchmark
-    let poll = Duration::from_millis(500);
-    loop {
-        let status = child
-            .wait_timeout(poll)
-            .context("failed to wait for child process")?;
-
-        let with_bench_status = | exit_status: Option<i32>,
-                                 status: BenchmarkStatus|
-         -> Result<BenchRunResult<P>, Error> {
-
-            // TODO collect output files from pwd 
-            Ok(BenchRunResult {
-                run: run.clone(),
-                status,
-                time: start.elapsed(),
-                stdout: crate::dao::read_vec(&stdout)?,
-                stderr: crate::dao::read_vec(&stderr)?,
-                files: crate::dao::read_file_conts(&pwd)?,
-                exit_status,
-            })
-        };
+    let monitored = limits.stdout.is_some() || limits.stderr.is_some() || limits.files.is_some();
+    // the memory of a run is polled often, as it may grow quickly between two polls
+    let poll = if run.memory_limit.is_some() {
+        Duration::from_millis(10)
+    } else if monitored {
+        Duration::from_millis(100)
+    } else {
+        Duration::from_millis(500)
+    };
+
+    let kill = |child: &std::process::Child| -> Result<ResourceUsage, Error> {
+        let (_, usage) = crate::process::kill_group(child, conf.grace_period)?;
+        Ok(usage)
+    };
+    let output_exceeded = || {
+        (limits.on_exceeded == OnOutputLimit::Kill && captures.iter().any(Capture::exceeded))
+            || matches!(limits.files, Some(limit) if dir_size(&pwd) > limit)
+    };
 
-        match status {
-            Some(status) => {
-                return if !status.success() && shall_terminate() {
-                    Err(Error::TermSignal(TermSignal))
-                } else {
-                    with_bench_status(status.code(), BenchmarkStatus::Success)
+    let (exit_status, usage, mut status) = loop {
+        match crate::process::wait_timeout(&child, poll)? {
+            Some((status, usage)) => {
+                if !status.success() && shall_terminate() {
+                    return Err(Error::TermSignal(TermSignal));
                 }
+                break (status.code(), usage, BenchmarkStatus::Success);
             }
             None => {
                 if shall_terminate() {
-                    child.kill().context("failed to kill child process")?;
+                    kill(&child)?;
                     return Err(TermSignal)?;
                 }
                 if start.elapsed() > run.timeout.mul_f64(1.2) {
-                    child.kill().context("failed to kill child process")?;
-                    return with_bench_status(None, BenchmarkStatus::Timeout);
+                    let usage = kill(&child)?;
+                    break (None, usage, BenchmarkStatus::Timeout);
+                }
+                if let Some(limit) = memory_limit {
+                    let rss = crate::process::group_rss(child.id());
+                    if rss > limit {
+                        info!("memory limit exceeded ({} bytes): {}", rss, run.display_command());
+                        let usage = kill(&child)?;
+                        break (None, usage, BenchmarkStatus::MemoryOut);
+                    }
                 }
+                if output_exceeded() {
+                    info!("output limit exceeded: {}", run.display_command());
+                    let usage = kill(&child)?;
+                    break (None, usage, BenchmarkStatus::OutputLimitExceeded);
+                }
+            }
+        }
+    };
+    let time = start.elapsed();
+    // processes the solver left behind would keep running, and keep the captured streams open
+    crate::process::kill_remaining(&child)?;
+    let mut truncated = vec![];
+    for capture in captures {
+        let output = capture.output;
+        if capture.finish(conf.grace_period)? {
+            truncated.push(output);
+            if limits.on_exceeded == OnOutputLimit::Kill && status == BenchmarkStatus::Success {
+                status = BenchmarkStatus::OutputLimitExceeded;
             }
         }
     }
+    let files = if matches!(limits.files, Some(limit) if dir_size(&pwd) > limit) {
+        truncated.push(CapturedOutput::Files);
+        if status == BenchmarkStatus::Success {
+            status = BenchmarkStatus::OutputLimitExceeded;
+        }
+        vec![]
+    } else {
+        crate::dao::read_file_conts(&pwd, tmp.clone())?
+    };
+
+    Ok(BenchRunResult {
+        run: run.clone(),
+        status,
+        time,
+        usage: Some(usage),
+        inputs,
+        exit_status,
+        truncated,
+        env: Some(recorded),
+        requested_timeout: None,
+        stdout: Output::file(stdout, tmp.clone()),
+        stderr: Output::file(stderr, tmp),
+        files,
+    })
+}
+
+/// A stream of a solver that is piped through a thread, which writes it to its file up to a size
+/// limit, followed by a marker, and discards the rest.
+struct Capture {
+    output: CapturedOutput,
+    limit: u64,
+    /// number of bytes the solver has written to the stream so far
+    written: Arc<std::sync::atomic::AtomicU64>,
+    /// the file the stream is written to. `None` once the capture has been abandoned.
+    out: Arc<std::sync::Mutex<Option<io::BufWriter<std::fs::File>>>>,
+    thread: std::thread::JoinHandle<io::Result<()>>,
+}
+
+impl Capture {
+    fn spawn(mut pipe: impl io::Read + Send + 'static, file: &Path, limit: u64, output: CapturedOutput) -> Result<Self, Error> {
+        use std::io::Write;
+        use std::sync::atomic::{AtomicU64, Ordering};
+        let out = Arc::new(std::sync::Mutex::new(Some(io::BufWriter::new(crate::dao::create_file(file)?))));
+        let written = Arc::new(AtomicU64::new(0));
+        let thread = {
+            let written = written.clone();
+            let out = out.clone();
+            std::thread::spawn(move || {
+                let mut buf = [0; 8192];
+                let mut total = 0;
+                loop {
+                    let n = match pipe.read(&mut buf) {
+                        Ok(0) => break,
+                        Ok(n) => n as u64,
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                        Err(e) => return Err(e),
+                    };
+                    let mut out = out.lock().unwrap();
+                    let out = match out.as_mut() {
+                        Some(out) => out,
+                        None => return Ok(()),
+                    };
+                    let keep = n.min(limit.saturating_sub(total));
+                    out.write_all(&buf[..keep as usize])?;
+                    if total <= limit && total + n > limit {
+                        write!(out, "\n[truncated: output exceeded the limit of {} bytes]\n", limit)?;
+                    }
+                    total += n;
+                    written.store(total, Ordering::SeqCst);
+                }
+                match out.lock().unwrap().as_mut() {
+                    Some(out) => out.flush(),
+                    None => Ok(()),
+                }
+            })
+        };
+        Ok(Capture { output, limit, written, out, thread })
+    }
+
+    fn exceeded(&self) -> bool {
+        self.written.load(std::sync::atomic::Ordering::SeqCst) > self.limit
+    }
+
+    /// Waits until the stream has been closed, and returns whether it has been truncated. A
+    /// process that has left the solver's process group might keep the stream open, hence the
+    /// capture is abandoned after `timeout`, and the output written so far is kept.
+    fn finish(self, timeout: Duration) -> Result<bool, Error> {
+        use std::io::Write;
+        let Capture { output, limit, written, out, thread } = self;
+        let start = std::time::Instant::now();
+        while !thread.is_finished() && start.elapsed() < timeout {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        if thread.is_finished() {
+            thread.join()
+                .expect("capture thread panicked")
+                .with_context(|| format!("failed to capture {:?}", output))?;
+        } else {
+            warn!("{:?} is still open after the solver terminated, ignoring further output", output);
+            // the thread stops as soon as it reads from the stream again
+            if let Some(mut out) = out.lock().unwrap().take() {
+                out.flush().with_context(|| format!("failed to capture {:?}", output))?;
+            }
+        }
+        Ok(written.load(std::sync::atomic::Ordering::SeqCst) > limit)
+    }
+}
+
+/// Total size of the files in `dir` in bytes. Files that vanish while `dir` is being scanned are
+/// ignored.
+fn dir_size(dir: &Path) -> u64 {
+    std::fs::read_dir(dir).into_iter().flatten().flatten()
+        .map(|e| match e.file_type() {
+            Ok(t) if t.is_dir() => dir_size(&e.path()),
+            Ok(_) => e.metadata().map(|m| m.len()).unwrap_or(0),
+            Err(_) => 0,
+        })
+        .sum()
 }
 
 fn setup_ctrlc() {
@@ -3,8 +3,8 @@ use std::fs;
 use std::fs::*;
 use std::os::unix::fs::PermissionsExt;
 use std::collections::*;
-use crate::interface::solvers::Script;
-use crate::interface::ids::PathId;
+use crate::interface::solvers::{Script, Configured};
+use crate::interface::ids::{AnnotatedPath, PathId};
 
 struct TestPostpro;
 
@@ -91,12 +91,32 @@ fn test_all_ran() {
 
         let opts = Opts {
             bench_dir: bench_dir.path().to_owned(),
+            recursive: false,
+            include: vec![],
+            exclude: vec![],
+            extensions: vec![],
             solver_dir: solver_dir.path().to_owned(),
             outdir: out_dir.path().to_owned(),
-            // only_post_process: false,
+            only_post_process: false,
             timeout,
+            memory_limit: None,
+            stdout_limit: None,
+            stderr_limit: None,
+            files_limit: None,
+            on_output_limit: OnOutputLimit::Kill,
+            decompress: false,
+            stdin: StdinMode::Null,
+            clean_env: false,
+            keep_env: vec![],
+            env: vec![],
+            redact_env: vec![],
+            repetitions: 1,
             num_threads: None,
-        };
+            grace_period: 1000,
+            on_changed_inputs: ChangedInputs::Rerun,
+            reuse_timeouts: false,
+            store: DaoBackend::Files,
+            };
 
         let benchmarks: Vec<PathBuf> = benchmark_strings.iter()
             .map(PathBuf::from)
@@ -183,18 +203,1196 @@ fn test_all_ran() {
                 let BenchRunResult {
                     run: _,
                     time: _,
+                    usage,
+                    inputs: _,
                     stdout,
                     stderr,
                     status,
                     exit_status,
+                    truncated,
+                    env: _,
+                    requested_timeout: _,
                     files,
                 } = res;
-                assert_eq!(String::from_utf8(stdout.clone()).unwrap(), script_out(&s, &b, timeout));
-                assert_eq!(String::from_utf8(stderr.clone()).unwrap(), script_err(&s, &b, timeout));
+                assert_eq!(String::from_utf8(stdout.read().unwrap()).unwrap(), script_out(s, b, timeout));
+                assert_eq!(String::from_utf8(stderr.read().unwrap()).unwrap(), script_err(s, b, timeout));
                 assert_eq!(exit_status, Some(0));
+                assert!(usage.is_some());
                 assert_eq!(status, BenchmarkStatus::Success);
-                assert_eq!(files, vec![FileConts { name: touch_dir.join(&touch_file), bytes: vec![], }])
+                assert!(truncated.is_empty());
+                assert_eq!(files.len(), 1);
+                assert_eq!(files[0].name, touch_dir.join(&touch_file));
+                assert_eq!(files[0].conts.read().unwrap(), b"");
             }
         }
         true
     }
+
+/// Reads the stdout of `res` from the result store.
+fn stdout<P: Benchmarker>(res: &BenchRunResult<P>) -> String {
+    use std::io::Read;
+    let mut out = String::new();
+    res.stdout().unwrap().read_to_string(&mut out).unwrap();
+    out
+}
+
+/// Options using the default values for all optional parameters.
+fn default_opts(bench_dir: &Path, solver_dir: &Path, outdir: &Path) -> Opts {
+    Opts {
+        bench_dir: bench_dir.to_owned(),
+        recursive: false,
+        include: vec![],
+        exclude: vec![],
+        extensions: vec![],
+        solver_dir: solver_dir.to_owned(),
+        outdir: outdir.to_owned(),
+        only_post_process: false,
+        timeout: 10,
+        memory_limit: None,
+        stdout_limit: None,
+        stderr_limit: None,
+        files_limit: None,
+        on_output_limit: OnOutputLimit::Kill,
+        decompress: false,
+        stdin: StdinMode::Null,
+        clean_env: false,
+        keep_env: vec![],
+        env: vec![],
+        redact_env: vec![],
+        repetitions: 1,
+        num_threads: None,
+        grace_period: 1000,
+        on_changed_inputs: ChangedInputs::Rerun,
+        reuse_timeouts: false,
+        store: DaoBackend::Files,
+    }
+}
+
+/// Creates a benchmark directory containing the files `benchmarks`, given by their path relative
+/// to the directory and their contents.
+fn bench_dir(benchmarks: &[(&str, &str)]) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    for (name, conts) in benchmarks {
+        let file = dir.path().join(name);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(file, conts).unwrap();
+    }
+    dir
+}
+
+/// Creates a solver directory containing the single solver `solver`, which runs `script`.
+fn solver_dir(script: &str) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    write_script(&dir.path().join("solver"), script);
+    dir
+}
+
+/// Writes the executable `script` to `file`.
+fn write_script(file: &Path, script: &str) {
+    fs::write(file, script).unwrap();
+    fs::set_permissions(file, Permissions::from_mode(0o777)).unwrap();
+}
+
+/// Runs a single solver with the given script on a single empty benchmark. The results are
+/// stored in `out_dir`, which must outlive them, as their outputs are read from there.
+fn run_script(out_dir: &Path, script: &str, set_opts: impl FnOnce(&mut Opts)) -> Vec<BenchRunResult<TestPostpro>> {
+    let bench_dir = bench_dir(&[("benchmark", "")]);
+    let solver_dir = solver_dir(script);
+
+    let mut opts = default_opts(bench_dir.path(), solver_dir.path(), out_dir);
+    set_opts(&mut opts);
+    let proc = run_with_opts(TestPostpro::new(), opts).unwrap();
+    proc.1.into_iter().map(|(res, _)| res).collect()
+}
+
+fn run_single_script(out_dir: &Path, script: &str, set_opts: impl FnOnce(&mut Opts)) -> BenchRunResult<TestPostpro> {
+    let mut res = run_script(out_dir, script, set_opts);
+    assert_eq!(res.len(), 1);
+    res.pop().unwrap()
+}
+
+#[test]
+fn test_environment() {
+    let script = "#!/bin/bash\necho ${PATH:+inherited} ${FOO:-unset}\n";
+    let out_dir = tempfile::tempdir().unwrap();
+    let res = run_single_script(out_dir.path(), script, |_| {});
+    assert_eq!(stdout(&res), "inherited unset\n");
+    // inherited variables are recorded as well
+    let env = res.env().unwrap();
+    assert_eq!(env["PATH"], std::env::var("PATH").unwrap());
+    assert!(!env.contains_key("FOO"));
+
+    // results are rerun if their recorded environment has changed
+    let res = run_single_script(out_dir.path(), script, |o| o.env = vec!["FOO=bar".to_owned()]);
+    assert_eq!(stdout(&res), "inherited bar\n");
+    assert_eq!(res.env().unwrap()["FOO"], "bar");
+
+    // redacted variables are passed to the solver, but their values are not recorded
+    let out_dir = tempfile::tempdir().unwrap();
+    let res = run_single_script(out_dir.path(), script, |o| {
+        o.env = vec!["FOO=secret".to_owned()];
+        o.redact_env = vec!["F*".to_owned()];
+    });
+    assert_eq!(stdout(&res), "inherited secret\n");
+    let env = res.env().unwrap();
+    assert_eq!(env["FOO"], "<redacted>");
+    assert_eq!(env["PATH"], std::env::var("PATH").unwrap());
+
+    let out_dir = tempfile::tempdir().unwrap();
+    let res = run_single_script(out_dir.path(), script, |o| {
+        o.clean_env = true;
+        o.keep_env = vec!["PATH".to_owned()];
+        o.env = vec!["FOO=bar=baz".to_owned()];
+    });
+    assert_eq!(stdout(&res), "inherited bar=baz\n");
+    // the whole environment is recorded in a clean environment
+    let env = res.env().unwrap();
+    assert_eq!(env.keys().collect::<Vec<_>>(), vec!["FOO", "PATH"]);
+    assert_eq!(env["PATH"], std::env::var("PATH").unwrap());
+
+    // the environment is stored with the result
+    let dao = dao::create::<TestPostpro>(DaoConfig { outdir: out_dir.path().to_owned(), backend: DaoBackend::Files }).unwrap();
+    let stored = dao.read_result(res.run()).unwrap().unwrap();
+    assert_eq!(stored.env(), Some(env));
+
+    // variables set by the solver take precedence
+    let solver_dir = tempfile::tempdir().unwrap();
+    let bench_dir = bench_dir(&[("benchmark", "")]);
+    let manifest = solver_dir.path().join("solvers.toml");
+    fs::write(&manifest, r#"
+        [[solvers]]
+        id = "solver"
+        command = "/bin/bash"
+        args = ["-c", "echo $FOO $BAR"]
+        env = { FOO = "solver" }
+        "#).unwrap();
+    let out_dir = tempfile::tempdir().unwrap();
+    let opts = Opts {
+        clean_env: true,
+        env: vec!["FOO=global".to_owned(), "BAR=global".to_owned()],
+        ..default_opts(bench_dir.path(), &manifest, out_dir.path())
+    };
+    let TestReduced(_, results) = run_with_opts(ManifestPostpro, opts).unwrap();
+    assert_eq!(stdout(&results[0].0), "solver global\n");
+
+    let opts = Opts { env: vec!["FOO".to_owned()], ..default_opts(bench_dir.path(), &manifest, out_dir.path()) };
+    assert!(validate_opts::<ManifestPostpro>(opts).is_err());
+}
+
+#[test]
+fn test_memory_limit() {
+    let out_dir = tempfile::tempdir().unwrap();
+    // `tail` needs to buffer the whole input as it does not contain a newline
+    let script = "#!/bin/bash\nhead -c 1G /dev/zero | tail\n";
+    let res = run_single_script(out_dir.path(), script, |o| o.memory_limit = Some(50));
+    assert_eq!(res.benchmark_status(), BenchmarkStatus::MemoryOut);
+    // the solver is killed long before it could buffer the whole input
+    assert!(res.max_rss().unwrap() < 512 * 1024 * 1024, "{:?}", res.usage());
+
+    // a failing solver that stayed below the limit did not run out of memory
+    let below = "#!/bin/bash\nhead -c 28M /dev/zero | tail\nexit 1\n";
+    let res = run_single_script(tempfile::tempdir().unwrap().path(), below, |o| o.memory_limit = Some(40));
+    assert_eq!(res.benchmark_status(), BenchmarkStatus::Success);
+    assert_eq!(res.exit_status, Some(1));
+
+    // a result obtained with another memory limit is not reused
+    let res = run_single_script(out_dir.path(), script, |o| o.memory_limit = Some(40));
+    assert_eq!(res.benchmark_status(), BenchmarkStatus::MemoryOut);
+    assert_eq!(res.run().memory_limit, Some(40));
+
+    let dir = out_dir.path();
+    for limit in [0, u64::MAX] {
+        let opts = Opts { memory_limit: Some(limit), ..default_opts(dir, dir, dir) };
+        assert!(validate_opts::<TestPostpro>(opts).is_err());
+    }
+}
+
+#[test]
+fn test_output_limits() {
+    // prints forever, unless it is interrupted
+    let script = "#!/bin/bash\nwhile true; do echo yes; done\n";
+    let out_dir = tempfile::tempdir().unwrap();
+    let res = run_single_script(out_dir.path(), script, |o| o.stdout_limit = Some(1));
+    assert_eq!(res.benchmark_status(), BenchmarkStatus::OutputLimitExceeded);
+    assert_eq!(res.truncated(), &[CapturedOutput::Stdout]);
+    let out = stdout(&res);
+    assert!(out.ends_with("\n[truncated: output exceeded the limit of 1048576 bytes]\n"));
+    assert_eq!(out.lines().filter(|l| *l == "yes").count(), 1024 * 1024 / 4);
+
+    let out_dir = tempfile::tempdir().unwrap();
+    let res = run_single_script(out_dir.path(), "#!/bin/bash\nhead -c 2M /dev/zero\necho done >&2\n", |o| {
+        o.stdout_limit = Some(1);
+        o.on_output_limit = OnOutputLimit::Truncate;
+    });
+    assert_eq!(res.benchmark_status(), BenchmarkStatus::Success);
+    assert_eq!(res.truncated(), &[CapturedOutput::Stdout]);
+    assert_eq!(res.stderr.read().unwrap(), b"done\n");
+
+    let out_dir = tempfile::tempdir().unwrap();
+    let res = run_single_script(out_dir.path(), "#!/bin/bash\nhead -c 2M /dev/zero > file\n", |o| o.files_limit = Some(1));
+    assert_eq!(res.benchmark_status(), BenchmarkStatus::OutputLimitExceeded);
+    assert_eq!(res.truncated(), &[CapturedOutput::Files]);
+    assert_eq!(res.files().count(), 0);
+
+    // a process that left the process group of the solver keeps its stdout open
+    let out_dir = tempfile::tempdir().unwrap();
+    let start = std::time::Instant::now();
+    let res = run_single_script(out_dir.path(), "#!/bin/bash
+setsid sleep 30 &
+echo $! >&2
+echo done
+", |o| o.stdout_limit = Some(1));
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert_eq!(res.benchmark_status(), BenchmarkStatus::Success);
+    assert_eq!(stdout(&res), "done\n");
+    let pid: i32 = String::from_utf8(res.stderr.read().unwrap()).unwrap().trim().parse().unwrap();
+    unsafe { libc::kill(pid, libc::SIGKILL) };
+
+    let dir = out_dir.path();
+    let opts = Opts { stdout_limit: Some(u64::MAX), ..default_opts(dir, dir, dir) };
+    assert!(validate_opts::<TestPostpro>(opts).is_err());
+}
+
+#[test]
+fn test_timeout_kills_process_group() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let res = run_single_script(out_dir.path(), "#!/bin/bash\nsleep 100 &\necho $!\nwait\n", |o| o.timeout = 1);
+    assert_eq!(res.benchmark_status(), BenchmarkStatus::Timeout);
+    let pid: u32 = stdout(&res).trim().parse().unwrap();
+    let alive = fs::read_to_string(format!("/proc/{}/stat", pid))
+        .map(|stat| !stat.contains(") Z "))
+        .unwrap_or(false);
+    assert!(!alive, "background process {} survived the timeout", pid);
+}
+
+#[test]
+fn test_remaining_processes_are_killed() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let res = run_single_script(out_dir.path(), "#!/bin/bash
+sleep 100 &
+echo $!
+", |_| {});
+    assert_eq!(res.benchmark_status(), BenchmarkStatus::Success);
+    let pid: u32 = stdout(&res).trim().parse().unwrap();
+    let alive = fs::read_to_string(format!("/proc/{}/stat", pid))
+        .map(|stat| !stat.contains(") Z "))
+        .unwrap_or(false);
+    assert!(!alive, "background process {} survived the solver", pid);
+}
+
+#[test]
+fn test_repetitions() {
+    for store in [DaoBackend::Files, DaoBackend::Sqlite] {
+        let out_dir = tempfile::tempdir().unwrap();
+        let run = |repetitions| {
+            run_script(out_dir.path(), "#!/bin/bash\necho ${3:-none}\n", |o| {
+                o.repetitions = repetitions;
+                o.store = store;
+            })
+            .iter()
+            .map(|r| (r.repetition(), r.seed(), stdout(r)))
+            .sorted()
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(run(1), vec![(0, None, "none\n".to_owned())]);
+        // the result of the first repetition is kept when the job is repeated
+        assert_eq!(run(3), vec![
+            (0, None, "none\n".to_owned()),
+            (1, Some(1), "1\n".to_owned()),
+            (2, Some(2), "2\n".to_owned()),
+        ]);
+    }
+
+    // a benchmark named like the repetition of another one
+    let bench_dir = bench_dir(&[("p", "p"), ("p.rep1", "p.rep1")]);
+    let solver_dir = solver_dir("#!/bin/bash\necho $(cat $1) ${3:-none}\n");
+    let out_dir = tempfile::tempdir().unwrap();
+    let opts = Opts { repetitions: 2, ..default_opts(bench_dir.path(), solver_dir.path(), out_dir.path()) };
+    run_with_opts(TestPostpro::new(), opts.clone()).unwrap();
+    let TestReduced(_, results) = report_with_opts(TestPostpro::new(), opts).unwrap();
+    let outputs = results.iter().map(|(res, _)| stdout(res)).sorted().collect::<Vec<_>>();
+    assert_eq!(outputs, vec!["p 0\n", "p 1\n", "p.rep1 0\n", "p.rep1 1\n"]);
+}
+
+#[test]
+fn test_solver_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = dir.path().join("solvers.toml");
+    fs::write(&manifest, r#"
+        [[solvers]]
+        id = "default"
+        command = "bin/solver"
+
+        [[solvers]]
+        id = "templated"
+        command = "solver"
+        args = ["--timeout={timeout}", "--seed", "{seed}", "-o", "{outdir}/proof", "{benchmark}"]
+        env = { OMP_NUM_THREADS = "1" }
+        "#).unwrap();
+    let bench = dir.path().join("bench.p");
+    fs::write(&bench, "").unwrap();
+    let bench = PathId::from_dir(&bench).unwrap();
+
+    let solvers: Vec<Arc<Configured>> = FromDir::from_dir(&manifest).unwrap();
+    assert_eq!(solvers.len(), 2);
+    assert_eq!(solvers[0].command(), dir.path().canonicalize().unwrap().join("bin/solver"));
+    assert_eq!(
+        solvers[0].args(&bench, &Duration::from_secs(5), None, "out"),
+        vec![bench.as_ref().display().to_string(), "5".to_owned()]);
+    assert_eq!(solvers[1].command(), Path::new("solver"));
+    assert_eq!(solvers[1].env()["OMP_NUM_THREADS"], "1");
+    assert_eq!(
+        solvers[1].args(&bench, &Duration::from_secs(5), Some(3), "out"),
+        vec!["--timeout=5", "--seed", "3", "-o", "out/proof", &bench.as_ref().display().to_string()]);
+
+    fs::write(&manifest, "[[solvers]]\nid = \"x\"\ncommand = \"x\"\nargs = [\"{unknown}\"]\n").unwrap();
+    assert!(Vec::<Arc<Configured>>::from_dir(&manifest).is_err());
+
+    // paths relative to a manifest that is given by a relative path still work when the solver is
+    // run within another directory
+    fs::create_dir(dir.path().join("bin")).unwrap();
+    write_script(&dir.path().join("bin").join("solver"), "#!/bin/bash\npwd\n");
+    fs::write(&manifest, "[[solvers]]\nid = \"x\"\ncommand = \"bin/solver\"\nworking_dir = \"bin\"\n").unwrap();
+    let cwd = std::env::current_dir().unwrap();
+    let relative = cwd.components().skip(1).map(|_| Path::new("..")).collect::<PathBuf>()
+        .join(manifest.strip_prefix("/").unwrap());
+    let bench_dir = bench_dir(&[("benchmark", "")]);
+    let out_dir = tempfile::tempdir().unwrap();
+    let opts = default_opts(bench_dir.path(), &relative, out_dir.path());
+    let TestReduced(_, results) = run_with_opts(ManifestPostpro, opts).unwrap();
+    let bin = dir.path().join("bin").canonicalize().unwrap();
+    assert_eq!(stdout(&results[0].0), format!("{}\n", bin.display()));
+}
+
+/// Runs solvers defined in a manifest.
+struct ManifestPostpro;
+
+impl Benchmarker for ManifestPostpro {
+    type Solver = Configured;
+    type Benchmark = PathId;
+    type Mapped = BenchRunResult<Self>;
+    type Reduced = TestReduced<Self>;
+
+    fn map(&self, r: &BenchRunResult<Self>) -> Result<Self::Mapped> {
+        Ok(r.clone())
+    }
+
+    fn reduce(&self, conf: &JobConfig<Self>, iter: impl IntoIterator<Item=(BenchRunResult<Self>, Self::Mapped)>) -> Result<Self::Reduced> {
+        Ok(TestReduced(conf.clone(), iter.into_iter().collect()))
+    }
+}
+
+#[test]
+fn test_solver_stdin() {
+    use std::io::Write;
+    let bench_dir = bench_dir(&[("p.smt2", "(check-sat)\n")]);
+    let solver_dir = tempfile::tempdir().unwrap();
+    let out_dir = tempfile::tempdir().unwrap();
+    let mut gz = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+    gz.write_all(b"(get-model)\n").unwrap();
+    fs::write(bench_dir.path().join("q.smt2.gz"), gz.finish().unwrap()).unwrap();
+    let manifest = solver_dir.path().join("solvers.toml");
+    fs::write(&manifest, r#"
+        [[solvers]]
+        id = "stdin"
+        command = "cat"
+        args = []
+        stdin = "benchmark"
+
+        [[solvers]]
+        id = "null"
+        command = "cat"
+        args = []
+        "#).unwrap();
+
+    let opts = Opts { decompress: true, ..default_opts(bench_dir.path(), &manifest, out_dir.path()) };
+    let TestReduced(_, results) = run_with_opts(ManifestPostpro, opts).unwrap();
+    let results = results.into_iter()
+        .map(|(res, _)| (res.solver().id().clone(), res.benchmark().to_string(), stdout(&res)))
+        .sorted()
+        .collect::<Vec<_>>();
+    assert_eq!(results, vec![
+        ("null".to_owned(), "p.smt2".to_owned(), "".to_owned()),
+        ("null".to_owned(), "q.smt2.gz".to_owned(), "".to_owned()),
+        ("stdin".to_owned(), "p.smt2".to_owned(), "(check-sat)\n".to_owned()),
+        ("stdin".to_owned(), "q.smt2.gz".to_owned(), "(get-model)\n".to_owned()),
+    ]);
+
+    let solvers: Vec<Arc<Configured>> = FromDir::from_dir(&manifest).unwrap();
+    let bench = PathId::from_dir(bench_dir.path().join("p.smt2")).unwrap();
+    let shown = <Configured as Solver<ManifestPostpro>>::show_command(&solvers[0], &bench, &Duration::from_secs(5), None);
+    assert_eq!(shown, format!("cat < {}", bench.as_ref().display()));
+
+    // solvers that do not set stdin themselves, such as scripts, follow the `--stdin` option
+    let script_dir = tempfile::tempdir().unwrap();
+    write_script(&script_dir.path().join("solver"), "#!/bin/bash\ncat\n");
+    let out_dir = tempfile::tempdir().unwrap();
+    let opts = Opts {
+        decompress: true,
+        stdin: StdinMode::Benchmark,
+        ..default_opts(bench_dir.path(), script_dir.path(), out_dir.path())
+    };
+    let TestReduced(_, results) = run_with_opts(TestPostpro::new(), opts).unwrap();
+    let outputs = results.iter().map(|(res, _)| stdout(res)).sorted().collect::<Vec<_>>();
+    assert_eq!(outputs, vec!["(check-sat)\n", "(get-model)\n"]);
+
+    fs::write(&manifest, "[[solvers]]\nid = \"null\"\ncommand = \"cat\"\nargs = []\nstdin = \"null\"\n").unwrap();
+    let out_dir = tempfile::tempdir().unwrap();
+    let opts = Opts { stdin: StdinMode::Benchmark, ..default_opts(bench_dir.path(), &manifest, out_dir.path()) };
+    let TestReduced(_, results) = run_with_opts(ManifestPostpro, opts).unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|(res, _)| stdout(res).is_empty()));
+}
+
+#[test]
+fn test_solver_grid() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = dir.path().join("solvers.json");
+    let write_grid = |strategies: &[&str]| fs::write(&manifest, serde_json::json!({
+        "grids": [{
+            "id": "grid",
+            "command": "solver",
+            "args": ["--strategy", "{strategy}", "--pre", "{pre}", "{benchmark}"],
+            "grid": { "strategy": strategies, "pre": ["on", "off"] },
+        }]
+    }).to_string()).unwrap();
+
+    write_grid(&["a", "b"]);
+    let solvers: Vec<Arc<Configured>> = FromDir::from_dir(&manifest).unwrap();
+    let ids = solvers.iter().map(|s| s.id().clone()).sorted().collect::<Vec<_>>();
+    assert_eq!(ids, vec![
+        "grid--pre=off--strategy=a",
+        "grid--pre=off--strategy=b",
+        "grid--pre=on--strategy=a",
+        "grid--pre=on--strategy=b",
+    ]);
+    let s = solvers.iter().find(|s| s.id() == "grid--pre=on--strategy=b").unwrap();
+    assert_eq!(s.params()["strategy"], "b");
+    assert_eq!(s.args[..4], ["--strategy", "b", "--pre", "on"]);
+
+    write_grid(&["c", "a", "b"]);
+    let extended: Vec<Arc<Configured>> = FromDir::from_dir(&manifest).unwrap();
+    assert_eq!(extended.len(), 6);
+    for s in solvers {
+        assert!(extended.contains(&s));
+    }
+}
+
+#[test]
+fn test_rerun_changed_solver() {
+    let bench_dir = bench_dir(&[("benchmark", "")]);
+    let solver_dir = tempfile::tempdir().unwrap();
+    let out_dir = tempfile::tempdir().unwrap();
+    let solver = solver_dir.path().join("solver");
+
+    let run = |version: &str, on_changed_inputs: ChangedInputs| {
+        write_script(&solver, &format!("#!/bin/bash\necho {}\n", version));
+        let opts = Opts {
+            on_changed_inputs,
+            ..default_opts(bench_dir.path(), solver_dir.path(), out_dir.path())
+        };
+        let proc = run_with_opts(TestPostpro::new(), opts).unwrap();
+        assert_eq!(proc.1.len(), 1);
+        stdout(&proc.1[0].0)
+    };
+
+    assert_eq!(run("v1", ChangedInputs::Rerun), "v1\n");
+    assert_eq!(run("v2", ChangedInputs::Keep), "v1\n");
+    assert_eq!(run("v2", ChangedInputs::Rerun), "v2\n");
+    assert_eq!(run("v2", ChangedInputs::Rerun), "v2\n");
+}
+
+#[test]
+fn test_fingerprint_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    let solver = dir.path().join("solver");
+    let cache = crate::fingerprint::FingerprintCache::default();
+    fs::write(&solver, "v1").unwrap();
+    let v1 = cache.get(&solver).unwrap();
+    assert_eq!(cache.get(&solver).unwrap(), v1);
+    // a solver rebuilt while the runner is running is hashed again
+    fs::write(&solver, "v2.0").unwrap();
+    let v2 = cache.get(&solver).unwrap();
+    assert_ne!(v2.sha256, v1.sha256);
+    assert!(!cache.matches(&v1, &solver).unwrap());
+    assert!(cache.matches(&v2, &solver).unwrap());
+}
+
+#[test]
+fn test_reuse_timeouts() {
+    let bench_dir = bench_dir(&[("quick", "0"), ("slow", "100")]);
+    let solver_dir = solver_dir("#!/bin/bash\necho $2\nsleep $(cat $1)\n");
+    let out_dir = tempfile::tempdir().unwrap();
+
+    let run = |timeout: u64| {
+        let opts = Opts {
+            timeout,
+            reuse_timeouts: true,
+            ..default_opts(bench_dir.path(), solver_dir.path(), out_dir.path())
+        };
+        let proc = run_with_opts(TestPostpro::new(), opts).unwrap();
+        proc.1.into_iter()
+            .map(|(res, _)| {
+                assert_eq!(res.requested_timeout(), Duration::from_secs(timeout));
+                let name = res.benchmark().as_ref().file_name().unwrap().to_str().unwrap().to_owned();
+                (name, res.benchmark_status(), stdout(&res))
+            })
+            .sorted()
+            .collect::<Vec<_>>()
+    };
+
+    let res = |quick_timeout: &str, slow_status, slow_timeout: &str| vec![
+        ("quick".to_owned(), BenchmarkStatus::Success, format!("{}\n", quick_timeout)),
+        ("slow".to_owned(), slow_status, format!("{}\n", slow_timeout)),
+    ];
+
+    assert_eq!(run(2), res("2", BenchmarkStatus::Timeout, "2"));
+    // the quick result is reused, the slow one is rerun
+    assert_eq!(run(3), res("2", BenchmarkStatus::Timeout, "3"));
+    // the slow result is reused, the quick one is rerun
+    assert_eq!(run(1), res("1", BenchmarkStatus::Timeout, "2"));
+}
+
+#[test]
+fn test_benchmark_discovery() {
+    let bench_dir = bench_dir(&[
+        ("p0.smt2", ""), ("README", ""), ("a/p1.smt2", ""), ("b/p1.smt2", ""), ("b/c/p2.smt2", ""),
+        ("b/skip.smt2", ""),
+    ]);
+    let solver_dir = solver_dir("#!/bin/bash\necho $1\n");
+    let out_dir = tempfile::tempdir().unwrap();
+    // symlinked directories are followed, unless they have been searched already
+    let linked = tempfile::tempdir().unwrap();
+    fs::write(linked.path().join("p3.smt2"), "").unwrap();
+    std::os::unix::fs::symlink(linked.path(), bench_dir.path().join("linked")).unwrap();
+    std::os::unix::fs::symlink(bench_dir.path(), bench_dir.path().join("b").join("c").join("loop")).unwrap();
+
+    let run = |set_opts: &dyn Fn(&mut Opts)| {
+        let mut opts = default_opts(bench_dir.path(), solver_dir.path(), out_dir.path());
+        set_opts(&mut opts);
+        let TestReduced(_, results) = run_with_opts(TestPostpro::new(), opts).unwrap();
+        results.into_iter()
+            .map(|(res, _)| {
+                assert_eq!(stdout(&res).trim(), res.benchmark().as_ref().to_str().unwrap());
+                res.benchmark().to_string()
+            })
+            .sorted()
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(run(&|_| {}), vec!["README", "p0.smt2"]);
+    assert_eq!(run(&|o| {
+        o.recursive = true;
+        o.extensions = vec!["smt2".to_owned()];
+        o.exclude = vec!["skip*".to_owned()];
+    }), vec!["a/p1.smt2", "b/c/p2.smt2", "b/p1.smt2", "linked/p3.smt2", "p0.smt2"]);
+    assert_eq!(run(&|o| {
+        o.recursive = true;
+        o.include = vec!["b/**".to_owned()];
+    }), vec!["b/c/p2.smt2", "b/p1.smt2", "b/skip.smt2"]);
+
+    // equally named benchmarks in different directories do not share their results
+    let results = out_dir.path().join("solver").join("10");
+    for bench in &["a/p1.smt2", "b/p1.smt2", "b/c/p2.smt2"] {
+        let meta = results.join(bench).join("meta.json");
+        assert!(meta.exists(), "{} does not exist", meta.display());
+    }
+
+    // benchmarks of results stored before, are identified by their file name
+    let old: PathId = serde_json::from_str("\"/benchmarks/b/p1.smt2\"").unwrap();
+    assert_eq!(old.to_string(), "p1.smt2");
+    assert_eq!(old.as_ref(), Path::new("/benchmarks/b/p1.smt2"));
+}
+
+#[test]
+fn test_benchmark_manifests() {
+    let bench_dir = bench_dir(&[
+        ("a/p1.smt2", ""), ("b/p1.smt2", ""), ("b/p2.smt2", ""),
+        ("hard.txt", "# the hard ones\na/p1.smt2\n\n  b/p1.smt2\n"),
+    ]);
+    let solver_dir = solver_dir("#!/bin/bash\necho $1\n");
+    let out_dir = tempfile::tempdir().unwrap();
+
+    let list = bench_dir.path().join("hard.txt");
+    let opts = default_opts(&list, solver_dir.path(), out_dir.path());
+    let TestReduced(_, results) = run_with_opts(TestPostpro::new(), opts).unwrap();
+    let results = results.into_iter()
+        .map(|(res, _)| (res.benchmark().to_string(), stdout(&res)))
+        .sorted()
+        .collect::<Vec<_>>();
+    let file = |b: &str| format!("{}\n", bench_dir.path().join(b).canonicalize().unwrap().display());
+    assert_eq!(results, vec![
+        ("a/p1.smt2".to_owned(), file("a/p1.smt2")),
+        ("b/p1.smt2".to_owned(), file("b/p1.smt2")),
+    ]);
+
+    /// A benchmark that keeps the metadata of its manifest entry.
+    #[derive(Debug, PartialEq)]
+    struct WithMeta(String, Option<serde_json::Value>);
+    impl FromBenchFile for WithMeta {
+        fn from_bench_file(_: &Path, id: &Path) -> Result<Self> {
+            Ok(WithMeta(id.display().to_string(), None))
+        }
+        fn from_manifest_entry(_: &Path, id: &Path, meta: &serde_json::Map<String, serde_json::Value>) -> Result<Self> {
+            Ok(WithMeta(id.display().to_string(), meta.get("status").cloned()))
+        }
+    }
+    let manifest = bench_dir.path().join("b").join("manifest.json");
+    fs::write(&manifest, r#"["p1.smt2", {"path": "p2.smt2", "status": "unsat"}, "../a/p1.smt2"]"#).unwrap();
+    let read = |filter: &BenchmarkFilter| Vec::<Arc<WithMeta>>::from_bench_dir(&manifest, filter).unwrap()
+        .into_iter()
+        .map(|b| Arc::try_unwrap(b).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(read(&BenchmarkFilter::default()), vec![
+        WithMeta("p1.smt2".to_owned(), None),
+        WithMeta("p2.smt2".to_owned(), Some(serde_json::json!("unsat"))),
+        WithMeta("../a/p1.smt2".to_owned(), None),
+    ]);
+    let filter = BenchmarkFilter { exclude: vec![glob::Pattern::new("p1*").unwrap()], ..Default::default() };
+    assert_eq!(read(&filter), vec![WithMeta("p2.smt2".to_owned(), Some(serde_json::json!("unsat")))]);
+
+    fs::write(&manifest, r#"["missing.smt2"]"#).unwrap();
+    assert!(Vec::<Arc<WithMeta>>::from_bench_dir(&manifest, &BenchmarkFilter::default()).is_err());
+}
+
+#[test]
+fn test_decompress() {
+    use std::io::Write;
+    let bench_dir = tempfile::tempdir().unwrap();
+    let solver_dir = solver_dir("#!/bin/bash\nbasename $1\ngrep -ac check-sat $1\nexit 0\n");
+    let conts = b"(check-sat)\n";
+    let compress = |name: &str| -> Vec<u8> {
+        match name.rsplit('.').next().unwrap() {
+            "gz" => {
+                let mut e = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+                e.write_all(conts).unwrap();
+                e.finish().unwrap()
+            }
+            "xz" => {
+                let mut e = xz2::write::XzEncoder::new(vec![], 6);
+                e.write_all(conts).unwrap();
+                e.finish().unwrap()
+            }
+            "bz2" => {
+                let mut e = bzip2::write::BzEncoder::new(vec![], bzip2::Compression::default());
+                e.write_all(conts).unwrap();
+                e.finish().unwrap()
+            }
+            "zst" => zstd::encode_all(&conts[..], 0).unwrap(),
+            _ => conts.to_vec(),
+        }
+    };
+    let names = ["p.smt2", "p.smt2.bz2", "p.smt2.gz", "p.smt2.xz", "p.smt2.zst"];
+    for name in &names {
+        fs::write(bench_dir.path().join(name), compress(name)).unwrap();
+    }
+
+    let run = |decompress| {
+        let out_dir = tempfile::tempdir().unwrap();
+        let opts = Opts { decompress, ..default_opts(bench_dir.path(), solver_dir.path(), out_dir.path()) };
+        let TestReduced(_, results) = run_with_opts(TestPostpro::new(), opts).unwrap();
+        results.into_iter()
+            .map(|(res, _)| {
+                assert_eq!(res.files().count(), 0);
+                (res.benchmark().to_string(), stdout(&res))
+            })
+            .sorted()
+            .collect::<Vec<_>>()
+    };
+    let results = run(true);
+    let expected = names.iter()
+        .map(|n| (n.to_string(), "p.smt2\n1\n".to_owned()))
+        .collect::<Vec<_>>();
+    assert_eq!(results, expected);
+
+    // without decompression the solver is passed the compressed file
+    let results = run(false);
+    assert_eq!(results[1], ("p.smt2.bz2".to_owned(), "p.smt2.bz2\n0\n".to_owned()));
+
+    // decompressed benchmarks do not clash with the outputs of the run
+    fs::write(bench_dir.path().join("stdout.txt.gz"), compress("stdout.txt.gz")).unwrap();
+    let results = run(true);
+    assert!(results.contains(&("stdout.txt.gz".to_owned(), "stdout.txt\n1\n".to_owned())), "{:?}", results);
+}
+
+#[test]
+fn test_result_paths() {
+    use crate::dao::{check_ids, id_to_path};
+    assert_eq!(id_to_path("p1.smt2"), PathBuf::from("p1.smt2"));
+    assert_eq!(id_to_path("suiteA/p1.smt2"), PathBuf::from("suiteA/p1.smt2"));
+    for id in &["/abs/p1.smt2", "../p1.smt2", "a//p1.smt2", "a/./p1.smt2", "pwd", "x.err", ".hidden", "", "p1.smt2.rep1"] {
+        let path = id_to_path(id);
+        assert_eq!(path.components().count(), 1, "{} is stored at {}", id, path.display());
+        assert!(!path.to_str().unwrap().starts_with('.'));
+    }
+    assert_ne!(id_to_path("/a/p1.smt2"), id_to_path("/b/p1.smt2"));
+
+    assert!(check_ids("benchmark", ["a/p1.smt2", "b/p1.smt2", "/a/p1.smt2", "/b/p1.smt2"]).is_ok());
+    assert!(check_ids("benchmark", ["p1.smt2", "p1.smt2"]).is_err());
+    // repetition 1 of `p1.smt2` is stored at `p1.smt2.rep1`
+    assert_ne!(id_to_path("p1.smt2.rep1"), PathBuf::from("p1.smt2.rep1"));
+    assert_eq!(id_to_path("p1.smt2.rep"), PathBuf::from("p1.smt2.rep"));
+    assert_eq!(id_to_path("p1.smt2.repx"), PathBuf::from("p1.smt2.repx"));
+    // the results of `a/b` would be deleted together with the ones of `a`
+    assert!(check_ids("benchmark", ["a", "a/b"]).is_err());
+}
+
+#[test]
+fn test_migrate() {
+    for store in &[DaoBackend::Files, DaoBackend::Sqlite] {
+        let bench_dir = bench_dir(&[("a/p1.smt2", "a/p1.smt2"), ("b/p2.smt2", "b/p2.smt2")]);
+        let solver_dir = solver_dir("#!/bin/bash\ncat $1\n");
+        let out_dir = tempfile::tempdir().unwrap();
+        let opts = || Opts {
+            recursive: true,
+            store: *store,
+            ..default_opts(bench_dir.path(), solver_dir.path(), out_dir.path())
+        };
+        run_with_opts(TestPostpro::new(), opts()).unwrap();
+
+        // earlier versions identified benchmarks by their absolute path, and stored results of
+        // the directory based store under the benchmark's file name
+        for b in &["a/p1.smt2", "b/p2.smt2"] {
+            let file = bench_dir.path().join(b).canonicalize().unwrap();
+            match store {
+                DaoBackend::Files => {
+                    let results = out_dir.path().join("solver").join("10");
+                    let old = results.join(file.file_name().unwrap());
+                    fs::rename(results.join(b), &old).unwrap();
+                    let mut meta: serde_json::Value = read_json(old.join("meta.json")).unwrap();
+                    meta["run"]["benchmark"] = serde_json::to_value(&file).unwrap();
+                    write_json(create_file(old.join("meta.json")).unwrap(), &meta).unwrap();
+                }
+                DaoBackend::Sqlite => {
+                    let conn = rusqlite::Connection::open(out_dir.path().join("results.sqlite")).unwrap();
+                    let file = file.to_str().unwrap();
+                    for table in &["results", "files"] {
+                        conn.execute(&format!("UPDATE {} SET benchmark = ?1 WHERE benchmark = ?2", table), rusqlite::params![file, b]).unwrap();
+                    }
+                    conn.execute("UPDATE results SET meta = json_set(meta, '$.run.benchmark', ?1) WHERE benchmark = ?1", rusqlite::params![file]).unwrap();
+                }
+            }
+        }
+        let status = status_with_opts::<TestPostpro>(opts()).unwrap();
+        assert_eq!(status["solver"], SolverStatus { done: 0, failed: 0, missing: 2 });
+
+        assert_eq!(migrate_with_opts::<TestPostpro>(opts()).unwrap(), 2);
+        assert_eq!(migrate_with_opts::<TestPostpro>(opts()).unwrap(), 0);
+        let TestReduced(_, results) = report_with_opts(TestPostpro::new(), opts()).unwrap();
+        let results = results.into_iter()
+            .map(|(res, _)| (res.benchmark().to_string(), stdout(&res)))
+            .sorted()
+            .collect::<Vec<_>>();
+        assert_eq!(results, vec![
+            ("a/p1.smt2".to_owned(), "a/p1.smt2".to_owned()),
+            ("b/p2.smt2".to_owned(), "b/p2.smt2".to_owned()),
+        ]);
+    }
+}
+
+#[test]
+fn test_sqlite_store() {
+    let bench_dir = bench_dir(&[("benchmark", "")]);
+    let solver_dir = tempfile::tempdir().unwrap();
+    let files_dir = tempfile::tempdir().unwrap();
+    let sqlite_dir = tempfile::tempdir().unwrap();
+    let solver = solver_dir.path().join("solver");
+
+    let run = |version: &str, outdir: &Path, store: DaoBackend| {
+        write_script(&solver, &format!("#!/bin/bash\necho {}\nmkdir out\necho {} > out/file.txt\n", version, version));
+        let opts = Opts {
+            on_changed_inputs: ChangedInputs::Keep,
+            store,
+            ..default_opts(bench_dir.path(), solver_dir.path(), outdir)
+        };
+        let mut proc = run_with_opts(TestPostpro::new(), opts).unwrap();
+        assert_eq!(proc.1.len(), 1);
+        let (res, _) = proc.1.pop().unwrap();
+        assert_eq!(res.files.len(), 1);
+        assert_eq!(res.files[0].name, PathBuf::from("out/file.txt"));
+        assert_eq!(res.files[0].conts.read().unwrap(), res.stdout.read().unwrap());
+        stdout(&res)
+    };
+    let import = || import_with_opts::<TestPostpro>(ImportOpts {
+        outdir: sqlite_dir.path().to_owned(),
+        store: DaoBackend::Sqlite,
+        from: files_dir.path().to_owned(),
+    }).unwrap();
+
+    assert_eq!(run("v1", files_dir.path(), DaoBackend::Files), "v1\n");
+    assert_eq!(import(), 1);
+    assert!(sqlite_dir.path().join("results.sqlite").exists());
+    // results that are stored already are not imported again
+    assert_eq!(import(), 0);
+    fs::remove_dir_all(files_dir.path()).unwrap();
+    assert_eq!(run("v2", sqlite_dir.path(), DaoBackend::Sqlite), "v1\n");
+}
+
+#[test]
+fn test_outputs_are_read_from_store() {
+    use std::io::Read;
+    for store in &[DaoBackend::Files, DaoBackend::Sqlite] {
+        let out_dir = tempfile::tempdir().unwrap();
+        let res = run_single_script(out_dir.path(), "#!/bin/bash\necho out\necho err >&2\necho file > file.txt\n", |opts| {
+            opts.store = *store;
+        });
+        match (&res.stdout, store) {
+            (Output::File { path, .. }, DaoBackend::Files) => assert!(path.starts_with(out_dir.path())),
+            (Output::Blob { .. }, DaoBackend::Sqlite) => {}
+            (output, _) => panic!("unexpected output {:?}", output),
+        }
+        if *store == DaoBackend::Sqlite {
+            // outputs do not refer to their rowid, which changes e.g. if the database is vacuumed
+            let db = rusqlite::Connection::open(out_dir.path().join("results.sqlite")).unwrap();
+            db.execute_batch("UPDATE results SET rowid = rowid + 100; UPDATE files SET rowid = rowid + 100;").unwrap();
+        }
+        let mut err = String::new();
+        res.stderr().unwrap().read_to_string(&mut err).unwrap();
+        assert_eq!((stdout(&res), err), ("out\n".to_owned(), "err\n".to_owned()));
+        assert_eq!(res.files().collect::<Vec<_>>(), vec![Path::new("file.txt")]);
+        let mut file = String::new();
+        res.file("file.txt").unwrap().read_to_string(&mut file).unwrap();
+        assert_eq!(file, "file\n");
+        assert!(res.file("missing.txt").is_err());
+    }
+}
+
+#[test]
+fn test_incomplete_results_are_ignored() {
+    let bench_dir = bench_dir(&[("benchmark", "")]);
+    let out_dir = tempfile::tempdir().unwrap();
+    let log = out_dir.path().join("runs.log");
+    let solver_dir = solver_dir(&format!("#!/bin/bash\necho $1 >> {}\necho done\n", log.display()));
+
+    // a result that has been written partially before the runner was killed
+    let result_dir = out_dir.path().join("solver").join("10");
+    let tmp = result_dir.join(".benchmark.tmp");
+    fs::create_dir_all(&tmp).unwrap();
+    fs::write(tmp.join("stdout.txt"), "partial").unwrap();
+
+    let opts = default_opts(bench_dir.path(), solver_dir.path(), out_dir.path());
+    let proc = run_with_opts(TestPostpro::new(), opts).unwrap();
+    assert_eq!(proc.1.len(), 1);
+    assert_eq!(stdout(&proc.1[0].0), "done\n");
+    let entries = fs::read_dir(&result_dir).unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(entries, vec!["benchmark"]);
+
+    // the runner has been killed while replacing the result, after it has moved the old one away
+    let result = result_dir.join("benchmark");
+    fs::rename(&result, result_dir.join(".benchmark.old")).unwrap();
+    fs::create_dir_all(&tmp).unwrap();
+    fs::write(tmp.join("stdout.txt"), "partial").unwrap();
+    let opts = default_opts(bench_dir.path(), solver_dir.path(), out_dir.path());
+    let proc = run_with_opts(TestPostpro::new(), opts).unwrap();
+    assert_eq!(stdout(&proc.1[0].0), "done\n");
+    let entries = fs::read_dir(&result_dir).unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(entries, vec!["benchmark"]);
+    // the restored result has not been run again
+    assert_eq!(fs::read_to_string(&log).unwrap().lines().count(), 1);
+}
+
+#[test]
+fn test_shared_outdir() {
+    let bench_dir = bench_dir(&[("benchmark0", ""), ("benchmark1", ""), ("benchmark2", ""), ("benchmark3", "")]);
+    let out_dir = tempfile::tempdir().unwrap();
+    let log = out_dir.path().join("runs.log");
+    let solver_dir = solver_dir(&format!("#!/bin/bash\necho $1 >> {}\nsleep 0.2\n", log.display()));
+
+    // a lock left behind by a runner that has been killed
+    let result_dir = out_dir.path().join("solver").join("10");
+    fs::create_dir_all(&result_dir).unwrap();
+    let mut dead = std::process::Command::new("true").spawn().unwrap();
+    dead.wait().unwrap();
+    fs::write(
+        result_dir.join(".benchmark0.lock"),
+        format!(r#"{{ "host": "{}", "pid": {} }}"#, crate::process::hostname(), dead.id()),
+    ).unwrap();
+
+    let runners = (0..2).map(|_| {
+        let opts = default_opts(bench_dir.path(), solver_dir.path(), out_dir.path());
+        std::thread::spawn(move || run_with_opts(TestPostpro::new(), opts).unwrap().1.len())
+    }).collect::<Vec<_>>();
+    for runner in runners {
+        assert_eq!(runner.join().unwrap(), 4);
+    }
+    let runs = fs::read_to_string(&log).unwrap();
+    assert_eq!(runs.lines().count(), 4, "benchmarks have been run more than once:\n{}", runs);
+    assert!(!result_dir.join(".benchmark0.lock").exists());
+}
+
+#[test]
+fn test_render_report() {
+    let bench_dir = bench_dir(&[("benchmark", "")]);
+    let solver_dir = solver_dir("#!/bin/bash\necho done\n");
+    let out_dir = tempfile::tempdir().unwrap();
+
+    let opts = || default_opts(bench_dir.path(), solver_dir.path(), out_dir.path());
+    run_with_opts(TestPostpro::new(), opts()).unwrap();
+    let latest = out_dir.path().join("reports").join("latest");
+    let first = fs::read_link(&latest).unwrap();
+    // each job gets its own report, even if it finishes within the same second as another one
+    let reduced = run_with_opts(TestPostpro::new(), opts()).unwrap();
+    assert_ne!(fs::read_link(&latest).unwrap(), first);
+    assert!(latest.join("reduced.json").exists());
+    assert!(latest.join("summary.txt").exists());
+    assert_eq!(fs::read_dir(out_dir.path().join("reports")).unwrap().count(), 3);
+
+    // the solver must not be run again
+    fs::remove_file(solver_dir.path().join("solver")).unwrap();
+    let rendered = render_report::<TestPostpro>(&latest).unwrap();
+    assert_eq!(serde_json::to_value(rendered).unwrap(), serde_json::to_value(reduced).unwrap());
+}
+
+#[test]
+fn test_cli() {
+    use clap::CommandFactory;
+    Cli::command().debug_assert();
+    let cli = Cli::try_parse_from(["benchmark_runner", "show", "10", "solver", "benchmark"]).unwrap();
+    match cli {
+        Cli::Show(ShowOpts { opts, solver, benchmark }) => {
+            assert_eq!(opts.timeout, 10);
+            assert_eq!((solver.as_str(), benchmark.as_str()), ("solver", "benchmark"));
+        }
+        _ => panic!("expected show subcommand"),
+    }
+    let cli = Cli::try_parse_from(["benchmark_runner", "import", "--store", "sqlite", "old"]).unwrap();
+    match cli {
+        Cli::Import(ImportOpts { store, from, .. }) => assert_eq!((store, from), (DaoBackend::Sqlite, PathBuf::from("old"))),
+        _ => panic!("expected import subcommand"),
+    }
+}
+
+#[test]
+fn test_status_and_clean() {
+    let bench_dir = bench_dir(&[("ok", "ok"), ("fail", "fail"), ("gone", "gone")]);
+    let solver_dir = solver_dir("#!/bin/bash\necho done\n");
+    // a solver named like the temporary directories of stored results
+    write_script(&solver_dir.path().join("vampire.old"), "#!/bin/bash\necho done\n");
+    let out_dir = tempfile::tempdir().unwrap();
+    let opts = || default_opts(bench_dir.path(), solver_dir.path(), out_dir.path());
+
+    let status = status_with_opts::<TestPostpro>(opts()).unwrap();
+    assert_eq!(status["solver"], SolverStatus { done: 0, failed: 0, missing: 3 });
+
+    run_with_opts(TestPostpro::new(), opts()).unwrap();
+    fs::write(bench_dir.path().join("new"), "new").unwrap();
+    let status = status_with_opts::<TestPostpro>(opts()).unwrap();
+    assert_eq!(status["solver"], SolverStatus { done: 3, failed: 0, missing: 1 });
+
+    // a result that has been removed because it failed, and one of a removed benchmark
+    let results = out_dir.path().join("solver").join("10");
+    fs::remove_file(bench_dir.path().join("gone")).unwrap();
+    fs::rename(results.join("fail"), results.join("fail.err")).unwrap();
+    // a result that has not been stored completely
+    fs::create_dir(results.join(".ok.tmp")).unwrap();
+    let status = status_with_opts::<TestPostpro>(opts()).unwrap();
+    assert_eq!(status["solver"], SolverStatus { done: 1, failed: 1, missing: 1 });
+    assert!(results.join(".ok.tmp").exists());
+
+    let entries = || {
+        let mut entries = fs::read_dir(&results).unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        entries.sort();
+        entries
+    };
+    // results of runs claimed by a live runner instance are kept
+    let owner = format!(r#"{{ "host": "{}", "pid": {} }}"#, crate::process::hostname(), std::process::id());
+    for lock in &[".fail.lock", ".gone.lock"] {
+        fs::write(results.join(lock), &owner).unwrap();
+    }
+    clean_with_opts::<TestPostpro>(opts()).unwrap();
+    assert_eq!(entries(), vec![".fail.lock", ".gone.lock", "fail.err", "gone", "ok"]);
+
+    for lock in &[".fail.lock", ".gone.lock"] {
+        fs::remove_file(results.join(lock)).unwrap();
+    }
+    // a complete result that has been moved away while it was replaced is restored
+    fs::rename(results.join("ok"), results.join(".ok.old")).unwrap();
+    clean_with_opts::<TestPostpro>(opts()).unwrap();
+    assert_eq!(entries(), vec!["ok"]);
+    let vampire = fs::read_dir(out_dir.path().join("vampire.old").join("10")).unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .sorted()
+        .collect::<Vec<_>>();
+    assert_eq!(vampire, vec!["fail", "ok"]);
+}
+
+#[test]
+fn test_only_post_process() {
+    let bench_dir = bench_dir(&[("old", "")]);
+    let solver_dir = solver_dir("#!/bin/bash\necho $1\n");
+    let out_dir = tempfile::tempdir().unwrap();
+    let opts = || default_opts(bench_dir.path(), solver_dir.path(), out_dir.path());
+    run_with_opts(TestPostpro::new(), opts()).unwrap();
+
+    // the solver fails if it is launched again
+    write_script(&solver_dir.path().join("solver"), "#!/bin/bash\nexit 1\n");
+    fs::write(bench_dir.path().join("new"), "").unwrap();
+    let proc = run_with_opts(TestPostpro::new(), Opts { only_post_process: true, ..opts() }).unwrap();
+    assert_eq!(proc.1.len(), 1);
+    assert!(proc.1[0].0.benchmark().as_ref().ends_with("old"));
+    assert!(!out_dir.path().join("solver").join("10").join("new").exists());
+    assert_eq!(report_with_opts(TestPostpro::new(), opts()).unwrap().1.len(), 1);
+}
+
+/// Like `TestPostpro`, but counts how often `map` is invoked, optionally caches mapped values,
+/// and fails to map results whose stdout is `bad`.
+struct CountingPostpro {
+    version: Option<&'static str>,
+    calls: Arc<std::sync::atomic::AtomicUsize>,
+}
+
+impl Benchmarker for CountingPostpro {
+    type Solver = Script;
+    type Benchmark = PathId;
+    type Mapped = BenchRunResult<Self>;
+    type Reduced = TestReduced<Self>;
+
+    fn map(&self, r: &BenchRunResult<Self>) -> Result<Self::Mapped> {
+        self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        if stdout(r) == "bad\n" {
+            bail!("bad result");
+        }
+        Ok(r.clone())
+    }
+
+    fn map_cache(&self) -> Option<MapCache<Self>> {
+        self.version.map(MapCache::new)
+    }
+
+    fn reduce(&self, conf: &JobConfig<Self>, iter: impl IntoIterator<Item=(BenchRunResult<Self>, Self::Mapped)>) -> Result<Self::Reduced> {
+        Ok(TestReduced(conf.clone(), iter.into_iter().collect()))
+    }
+}
+
+#[test]
+fn test_map_cache() {
+    for store in &[DaoBackend::Files, DaoBackend::Sqlite] {
+        let bench_dir = bench_dir(&[("a", ""), ("b", "")]);
+        let solver_dir = solver_dir("#!/bin/bash\necho $1\n");
+        let out_dir = tempfile::tempdir().unwrap();
+
+        let map_calls = |version, timeout| {
+            let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+            let post = CountingPostpro { version: Some(version), calls: calls.clone() };
+            let opts = Opts {
+                store: *store,
+                timeout,
+                reuse_timeouts: true,
+                ..default_opts(bench_dir.path(), solver_dir.path(), out_dir.path())
+            };
+            let TestReduced(_, results) = run_with_opts(post, opts).unwrap();
+            for (res, mapped) in results {
+                assert_eq!(stdout(&res), stdout(&mapped));
+            }
+            calls.load(std::sync::atomic::Ordering::SeqCst)
+        };
+        assert_eq!(map_calls("v1", 10), 2);
+        assert_eq!(map_calls("v1", 10), 0);
+        assert_eq!(map_calls("v2", 10), 2);
+        // results reused for another timeout are cached where they are stored
+        assert_eq!(map_calls("v2", 20), 0);
+    }
+}
+
+#[test]
+fn test_map_while_running() {
+    let bench_dir = bench_dir(&[("good", "good"), ("bad", "bad")]);
+    let solver_dir = solver_dir("#!/bin/bash\ncat $1; echo\n");
+    let out_dir = tempfile::tempdir().unwrap();
+
+    let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let post = CountingPostpro { version: None, calls: calls.clone() };
+    let opts = default_opts(bench_dir.path(), solver_dir.path(), out_dir.path());
+    let TestReduced(_, results) = run_with_opts(post, opts).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(stdout(&results[0].0), "good\n");
+    // each result is mapped once, and the one that failed to be mapped has been removed
+    assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+    assert!(out_dir.path().join("solver").join("10").join("bad.err").exists());
+}
+
+/// Reports the first line of the stdout of each run as its answer, unless it is `unknown`.
+struct AnswerPostpro;
+
+impl Benchmarker for AnswerPostpro {
+    type Solver = Script;
+    type Benchmark = AnnotatedPath;
+    type Mapped = BenchRunResult<Self>;
+    type Reduced = TestReduced<Self>;
+
+    fn map(&self, r: &BenchRunResult<Self>) -> Result<Self::Mapped> {
+        Ok(r.clone())
+    }
+
+    fn map_cache(&self) -> Option<MapCache<Self>> {
+        Some(MapCache::new("v1"))
+    }
+
+    fn answer(&self, r: &BenchRunResult<Self>, _: &Self::Mapped) -> Option<String> {
+        Some(stdout(r).lines().next()?.to_owned())
+    }
+
+    fn reduce(&self, conf: &JobConfig<Self>, iter: impl IntoIterator<Item=(BenchRunResult<Self>, Self::Mapped)>) -> Result<Self::Reduced> {
+        Ok(TestReduced(conf.clone(), iter.into_iter().collect()))
+    }
+}
+
+#[test]
+fn test_soundness_issues() {
+    let bench_dir = bench_dir(&[
+        ("a", ""), ("b", ""), ("c", ""), ("d", ""),
+        ("a.meta.json", r#"{"status": "sat", "rating": 0.5, "source": "test"}"#),
+        ("c.meta.json", r#"{"status": "unsat"}"#),
+        ("d.meta.json", r#"{"status": "SAT"}"#),
+    ]);
+    let solver_dir = solver_dir("#!/bin/bash\ncase $(basename $1) in c) echo unknown;; *) echo sat;; esac\n");
+    let out_dir = tempfile::tempdir().unwrap();
+    let latest = out_dir.path().join("reports").join("latest");
+    let issues = || -> Vec<SoundnessIssue> { read_json(latest.join("soundness.json")).unwrap() };
+
+    // sidecar files are no benchmarks
+    let opts = default_opts(bench_dir.path(), solver_dir.path(), out_dir.path());
+    let TestReduced(_, results) = run_with_opts(AnswerPostpro, opts).unwrap();
+    let benchmarks = results.iter()
+        .map(|(res, _)| res.benchmark().clone())
+        .sorted()
+        .collect::<Vec<_>>();
+    assert_eq!(benchmarks.iter().map(|b| b.to_string()).collect::<Vec<_>>(), vec!["a", "b", "c", "d"]);
+    assert_eq!(benchmarks[0].expected(), Some("sat"));
+    assert_eq!(benchmarks[0].difficulty(), Some(0.5));
+    assert_eq!(benchmarks[0].meta().other.get("source").map(String::as_str), Some("test"));
+    assert_eq!(benchmarks[1].expected(), None);
+    // `c` has no definite answer, and answers are compared ignoring case
+    assert_eq!(issues(), vec![]);
+
+    // the manifest updates the expected results of the stored runs
+    let manifest = bench_dir.path().join("manifest.json");
+    fs::write(&manifest, r#"[{"path": "a", "status": "unsat"}, {"path": "b", "status": "sat"}, "c", "d"]"#).unwrap();
+    let opts = || Opts { only_post_process: true, ..default_opts(&manifest, solver_dir.path(), out_dir.path()) };
+    let TestReduced(_, results) = run_with_opts(AnswerPostpro, opts()).unwrap();
+    // the stored results keep the metadata they have been run with
+    assert!(results.iter().all(|(res, _)| res.benchmark().expected() != Some("unsat")
+        || res.benchmark().to_string() == "c"));
+    assert_eq!(issues(), vec![SoundnessIssue {
+        solver: "solver".to_owned(),
+        benchmark: "a".to_owned(),
+        repetition: 0,
+        expected: "unsat".to_owned(),
+        answer: "sat".to_owned(),
+    }]);
+    let summary = fs::read_to_string(latest.join("summary.txt")).unwrap();
+    assert!(summary.contains("solver answered sat on a, but unsat is expected"), "{}", summary);
+
+    // cached mapped values of results whose metadata has been migrated are not used anymore.
+    // the results stay where they are, as only their metadata changes.
+    assert_eq!(migrate_with_opts::<AnswerPostpro>(opts()).unwrap(), 0);
+    let TestReduced(_, results) = run_with_opts(AnswerPostpro, opts()).unwrap();
+    let (_, mapped) = results.iter().find(|(res, _)| res.benchmark().to_string() == "a").unwrap();
+    assert_eq!(mapped.benchmark().expected(), Some("unsat"));
+}